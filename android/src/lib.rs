@@ -1,11 +1,62 @@
-#![cfg_attr(not(target_os = "android"), allow(unused_imports))]
+#![cfg_attr(not(target_os = "android"), allow(unused_imports, dead_code))]
 
 use batbox_android as android;
+use jni::{
+    objects::{JObject, JString},
+    JNIEnv, JavaVM,
+};
+
+/// Calls a method of the activity on the current thread, attaching it to the vm once
+fn with_activity<T>(f: impl FnOnce(&mut JNIEnv, &JObject) -> jni::errors::Result<T>) -> Option<T> {
+    let context = ndk_context::android_context();
+    let result = (|| {
+        let vm = unsafe { JavaVM::from_raw(context.vm().cast()) }?;
+        let mut env = vm.attach_current_thread_permanently()?;
+        let activity = unsafe { JObject::from_raw(context.context().cast()) };
+        f(&mut env, &activity)
+    })();
+    result
+        .map_err(|e| log::error!("activity call failed: {e}"))
+        .ok()
+}
+
+/// False while the app is in the background or covered, like by the notification shade
+fn activity_focused() -> bool {
+    with_activity(|env, activity| env.call_method(activity, "hasWindowFocus", "()Z", &[])?.z())
+        .unwrap_or(true)
+}
+
+/// Default locale of the app, which follows the device language
+fn device_language() -> Option<String> {
+    with_activity(|env, _| {
+        let locale = env
+            .call_static_method(
+                "java/util/Locale",
+                "getDefault",
+                "()Ljava/util/Locale;",
+                &[],
+            )?
+            .l()?;
+        let tag = env
+            .call_method(&locale, "toLanguageTag", "()Ljava/lang/String;", &[])?
+            .l()?;
+        Ok(env.get_string(&JString::from(tag))?.into())
+    })
+}
+
+fn finish_activity() {
+    with_activity(|env, activity| env.call_method(activity, "finish", "()V", &[])?.v());
+}
 
 #[cfg(target_os = "android")]
 #[no_mangle]
 fn android_main(app: android::App) {
     android::init(app);
     android::set_file_mode(android::FileMode::Assets);
+    meowjelly::set_android_activity(meowjelly::AndroidActivity {
+        focused: activity_focused,
+        finish: finish_activity,
+        language: device_language,
+    });
     meowjelly::main();
 }
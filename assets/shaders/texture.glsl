@@ -1,7 +1,8 @@
 varying vec2 v_uv;
 varying vec3 v_camera_pos;
 varying vec3 v_world_pos;
-varying float v_edge;
+varying vec3 v_normal;
+varying vec3 v_view_normal;
 
 #ifdef VERTEX_SHADER
 attribute vec3 a_pos;
@@ -12,8 +13,10 @@ uniform mat3 u_uv_matrix;
 uniform mat4 u_model_matrix;
 uniform mat4 u_view_matrix;
 uniform mat4 u_projection_matrix;
+uniform mat3 u_normal_matrix;
 void main() {
-  v_edge = 1.0 - abs(a_normal.z);
+  v_normal = u_normal_matrix * a_normal;
+  v_view_normal = (u_view_matrix * vec4(v_normal, 0.0)).xyz;
   v_uv = (u_uv_matrix * vec3(a_uv, 1.0)).xy;
   v_world_pos = (u_model_matrix * vec4(a_pos, 1.0)).xyz;
   v_camera_pos = (u_view_matrix * vec4(v_world_pos, 1.0)).xyz;
@@ -29,21 +32,63 @@ uniform float u_fog_distance;
 uniform vec4 u_fog_color;
 uniform vec3 u_player_pos;
 uniform float u_player_radius;
-void main() {
-  float fog = clamp(-v_camera_pos.z / u_fog_distance, 0.0, 1.0);
-  float light = 1.0;
-  if (length(v_world_pos.xy) >= 9.9) {
-    if (length(cross(normalize(v_world_pos - vec3(0.0, 0.0, u_player_pos.z)), vec3(u_player_pos.xy, 0.0))) < u_player_radius && dot(v_world_pos.xy, u_player_pos.xy) > 0.0) {
-      light *= 1.0 - clamp((length(u_player_pos.xy) - 3.0) / 6.0 * 0.5, 0.0, 1.0);
+uniform float u_wall_radius;
+uniform float u_wall_shadow_start;
+uniform float u_wall_shadow_end;
+uniform float u_wall_shadow_darkness;
+uniform float u_obstacle_shadow_start;
+uniform float u_obstacle_shadow_darkness;
+uniform float u_obstacle_shadow_range;
+uniform float u_obstacle_shadow_spread;
+uniform float u_ambient;
+uniform float u_diffuse;
+// Direction towards the light when w is 0, its position when w is 1
+uniform vec4 u_light;
+uniform float u_light_range;
+uniform float u_rim;
+uniform float u_rim_power;
+
+float lighting() {
+  vec3 normal = normalize(v_normal);
+  vec3 view_normal = normalize(v_view_normal);
+  vec3 to_camera = normalize(-v_camera_pos);
+  // Thick sprites are seen from both sides
+  if (dot(view_normal, to_camera) < 0.0) {
+    normal = -normal;
+    view_normal = -view_normal;
+  }
+  vec3 to_light = u_light.xyz - v_world_pos * u_light.w;
+  float attenuation = mix(1.0, clamp(1.0 - length(to_light) / u_light_range, 0.0, 1.0), u_light.w);
+  float diffuse = max(dot(normal, normalize(to_light)), 0.0) * attenuation;
+  float rim = pow(1.0 - max(dot(view_normal, to_camera), 0.0), u_rim_power);
+  return u_ambient + u_diffuse * diffuse + u_rim * rim;
+}
+
+// Light left after the player shadow
+float shadow() {
+  if (length(v_world_pos.xy) >= u_wall_radius) {
+    // Cast from the axis, darker the closer the player is to the wall
+    vec3 from_axis = normalize(v_world_pos - vec3(0.0, 0.0, u_player_pos.z));
+    bool behind_player = length(cross(from_axis, vec3(u_player_pos.xy, 0.0))) < u_player_radius
+      && dot(v_world_pos.xy, u_player_pos.xy) > 0.0;
+    if (behind_player) {
+      float t = clamp((length(u_player_pos.xy) - u_wall_shadow_start) / (u_wall_shadow_end - u_wall_shadow_start), 0.0, 1.0);
+      return 1.0 - t * u_wall_shadow_darkness;
     }
-  } else if (u_player_pos.z > v_world_pos.z + 1.0) {
-    float k = clamp((u_player_pos.z - v_world_pos.z) / 200.0, 0.0, 1.0);
-    if (length(u_player_pos.xy - v_world_pos.xy) < u_player_radius * (1.0 + k * 4.0)) {
-      light *= k + (1.0 - k) * 0.5;
+  } else if (u_player_pos.z > v_world_pos.z + u_obstacle_shadow_start) {
+    // Cast along the axis, growing and fading with the distance ahead
+    float k = clamp((u_player_pos.z - v_world_pos.z) / u_obstacle_shadow_range, 0.0, 1.0);
+    if (length(u_player_pos.xy - v_world_pos.xy) < u_player_radius * mix(1.0, u_obstacle_shadow_spread, k)) {
+      return 1.0 - (1.0 - k) * u_obstacle_shadow_darkness;
     }
   }
+  return 1.0;
+}
+
+void main() {
+  float fog = clamp(-v_camera_pos.z / u_fog_distance, 0.0, 1.0);
+  float light = lighting() * shadow();
   vec4 color = smoothTexture2D(v_uv, u_texture, u_texture_size) * u_color * vec4(light, light, light, 1.0);
-  color.rgb *= (1.0 - v_edge * 0.25);
   gl_FragColor = color * (1.0 - fog) + vec4(u_fog_color.rgb, color.a) * fog;
   if (gl_FragColor.a == 0.0) {
     discard;
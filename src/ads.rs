@@ -0,0 +1,121 @@
+use super::*;
+
+/// Something that can show fullscreen ads
+pub trait AdBackend {
+    /// Returns whether the ad was actually shown
+    fn show_fullscreen(&self) -> future::LocalBoxFuture<'_, anyhow::Result<bool>>;
+    /// Returns whether the reward was earned by watching till the end
+    fn show_rewarded(&self) -> future::LocalBoxFuture<'_, anyhow::Result<bool>>;
+}
+
+/// Decides when fullscreen ads are shown, state is kept for the whole session
+pub struct Ads {
+    backend: Option<Rc<dyn AdBackend>>,
+    /// Since the last shown ad or the start of the session
+    timer: RefCell<Timer>,
+    /// Finished runs since the last shown ad
+    runs: Cell<usize>,
+}
+
+impl Ads {
+    /// No ads are ever shown without a backend
+    pub fn new(backend: Option<Rc<dyn AdBackend>>) -> Self {
+        Self {
+            backend,
+            timer: RefCell::new(Timer::new()),
+            runs: Cell::new(0),
+        }
+    }
+
+    /// Called once per finished run, before [Self::should_show]
+    pub fn run_finished(&self) {
+        self.runs.set(self.runs.get() + 1);
+    }
+
+    /// Whether the frequency policy allows an ad after the finished run
+    pub fn should_show(&self, config: &config::Ads, run_time: f32, games_played: u64) -> bool {
+        self.backend.is_some()
+            && games_played >= config.grace_games
+            && run_time >= config.min_run_time
+            && self.runs.get() >= config.runs_between
+            && self.timer.borrow().elapsed().as_secs_f64() as f32 >= config.min_interval
+    }
+
+    /// Whether rewarded videos can be offered
+    pub fn available(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    /// Not limited by the frequency policy since the player asks for it
+    pub async fn show_rewarded(&self) -> anyhow::Result<bool> {
+        match &self.backend {
+            Some(backend) => backend.show_rewarded().await,
+            None => Ok(false),
+        }
+    }
+
+    pub async fn show_fullscreen(&self) -> anyhow::Result<bool> {
+        let Some(backend) = &self.backend else {
+            return Ok(false);
+        };
+        let shown = backend.show_fullscreen().await?;
+        if shown {
+            *self.timer.borrow_mut() = Timer::new();
+            self.runs.set(0);
+        }
+        Ok(shown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+
+    /// Always shows the ad
+    struct Shown;
+
+    impl AdBackend for Shown {
+        fn show_fullscreen(&self) -> future::LocalBoxFuture<'_, anyhow::Result<bool>> {
+            future::ready(Ok(true)).boxed_local()
+        }
+
+        fn show_rewarded(&self) -> future::LocalBoxFuture<'_, anyhow::Result<bool>> {
+            future::ready(Ok(true)).boxed_local()
+        }
+    }
+
+    const CONFIG: config::Ads = config::Ads {
+        min_interval: 0.0,
+        runs_between: 2,
+        min_run_time: 10.0,
+        grace_games: 1,
+    };
+
+    #[test]
+    fn runs_are_counted_separately() {
+        let ads = Ads::new(Some(Rc::new(Shown)));
+        ads.run_finished();
+        // Asking again does not count as another run
+        assert!(!ads.should_show(&CONFIG, 20.0, 5));
+        assert!(!ads.should_show(&CONFIG, 20.0, 5));
+        ads.run_finished();
+        assert!(ads.should_show(&CONFIG, 20.0, 5));
+        assert!(!ads.should_show(&CONFIG, 5.0, 5));
+        assert!(!ads.should_show(&CONFIG, 20.0, 0));
+
+        assert!(block_on(ads.show_fullscreen()).unwrap());
+        ads.run_finished();
+        assert!(!ads.should_show(&CONFIG, 20.0, 5));
+    }
+
+    #[test]
+    fn no_ads_without_backend() {
+        let ads = Ads::new(None);
+        ads.run_finished();
+        ads.run_finished();
+        assert!(!ads.should_show(&CONFIG, 20.0, 5));
+        assert!(!block_on(ads.show_rewarded()).unwrap());
+    }
+}
@@ -0,0 +1,264 @@
+use super::*;
+
+use overrides::{ConfigFile, Overrides};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+use validation::Problem;
+
+fn problem(file: &Path, message: impl std::fmt::Display) -> Problem {
+    Problem {
+        file: file.to_owned(),
+        key: None,
+        message: message.to_string(),
+    }
+}
+
+/// Files in the directory except the list itself
+fn dir_files(dir: &Path, problems: &mut Vec<Problem>) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            problems.push(problem(dir, e));
+            return Vec::new();
+        }
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name() != Some(std::ffi::OsStr::new("_list.ron")))
+        .collect()
+}
+
+async fn load_list(dir: &Path, problems: &mut Vec<Problem>) -> Vec<String> {
+    let path = dir.join("_list.ron");
+    match file::load_detect::<Vec<String>>(&path).await {
+        Ok(list) => {
+            let mut seen = HashSet::new();
+            for name in &list {
+                if !seen.insert(name) {
+                    problems.push(problem(&path, format_args!("{name:?} is listed twice")));
+                }
+            }
+            list
+        }
+        Err(e) => {
+            problems.push(problem(&path, format_args!("{e:#}")));
+            Vec::new()
+        }
+    }
+}
+
+fn check_strays(dir: &Path, expected: &HashSet<PathBuf>, problems: &mut Vec<Problem>) {
+    for path in dir_files(dir, problems) {
+        if !expected.contains(&path) {
+            problems.push(problem(&path, "stray file, not referenced in _list.ron"));
+        }
+    }
+}
+
+/// Whether the player can fit through the obstacle somewhere inside the tube
+fn has_opening(
+    image: &image::RgbaImage,
+    thickness: f32,
+    tilt: Angle<f32>,
+    tube_radius: f32,
+    player_radius: f32,
+) -> bool {
+    const GRID: usize = 128;
+    let size = vec2(image.width() as usize, image.height() as usize);
+    let inv = assets::Obstacle::base_transform(size, thickness, tube_radius, tilt).inverse();
+    let dir = (inv * vec4(0.0, 0.0, 1.0, 0.0)).xyz();
+    let cell = 2.0 * tube_radius / GRID as f32;
+    let cell_center =
+        |i: usize, j: usize| vec2(i, j).map(|x| (x as f32 + 0.5) * cell - tube_radius);
+    // Same as the game's hittest: vertical ray through the point hits an opaque pixel
+    let blocked: Vec<Vec<bool>> = (0..GRID)
+        .map(|i| {
+            (0..GRID)
+                .map(|j| {
+                    let from = (inv * cell_center(i, j).extend(0.0).extend(1.0)).xyz();
+                    let t = -from.z / dir.z;
+                    let vec2(x, y) = (from.xy() + dir.xy() * t).map(|x| x * 0.5 + 0.5);
+                    if x < 0.0 || y < 0.0 || x >= 1.0 || y >= 1.0 {
+                        return false;
+                    }
+                    let x = (x * size.x as f32) as u32;
+                    let y = (y * size.y as f32) as u32;
+                    image.get_pixel(x, y)[3] != 0
+                })
+                .collect()
+        })
+        .collect();
+    let r = (player_radius / cell).ceil() as isize;
+    (0..GRID).any(|i| {
+        (0..GRID).any(|j| {
+            if cell_center(i, j).len() > tube_radius - player_radius {
+                return false;
+            }
+            (-r..=r).all(|di| {
+                (-r..=r).all(|dj| {
+                    if ((di * di + dj * dj) as f32).sqrt() * cell > player_radius {
+                        return true;
+                    }
+                    let i = i as isize + di;
+                    let j = j as isize + dj;
+                    i < 0
+                        || j < 0
+                        || i >= GRID as isize
+                        || j >= GRID as isize
+                        || !blocked[i as usize][j as usize]
+                })
+            })
+        })
+    })
+}
+
+async fn check_obstacles(
+    dir: &Path,
+    tube_radius: f32,
+    player_radius: f32,
+    problems: &mut Vec<Problem>,
+) {
+    let mut expected = HashSet::new();
+    for name in load_list(dir, problems).await {
+        let png = dir.join(&name).with_extension("png");
+        let toml = dir.join(&name).with_extension("toml");
+        expected.insert(png.clone());
+        expected.insert(toml.clone());
+        let config: Option<assets::ObstacleConfig> = if toml.is_file() {
+            match validation::load(&toml).await {
+                Ok(config) => Some(config),
+                Err(validation::Problems(config_problems)) => {
+                    problems.extend(config_problems);
+                    None
+                }
+            }
+        } else {
+            problems.push(problem(&toml, "listed obstacle has no config"));
+            None
+        };
+        if !png.is_file() {
+            problems.push(problem(&png, "listed obstacle has no texture"));
+            continue;
+        }
+        let image = match image::open(&png) {
+            Ok(image) => image,
+            Err(e) => {
+                problems.push(problem(&png, e));
+                continue;
+            }
+        };
+        if !image.color().has_alpha() {
+            problems.push(problem(
+                &png,
+                "no alpha channel, obstacle would block everything",
+            ));
+            continue;
+        }
+        let image = image.to_rgba8();
+        if image.pixels().all(|pixel| pixel[3] == 0) {
+            problems.push(problem(
+                &png,
+                "fully transparent, obstacle would be invisible",
+            ));
+            continue;
+        }
+        let Some(config) = config else {
+            continue;
+        };
+        let size = vec2(image.width() as usize, image.height() as usize);
+        let tilts = match &config.tilt {
+            Some(tilt) => vec![tilt.min, tilt.max],
+            None => vec![assets::Obstacle::cover_tilt(size).as_degrees()],
+        };
+        for tilt in tilts {
+            let angle = Angle::from_degrees(tilt);
+            if !has_opening(&image, config.thickness, angle, tube_radius, player_radius) {
+                problems.push(problem(
+                    &png,
+                    format_args!(
+                        "no opening a player of radius {player_radius} fits through at {tilt:.1} degrees tilt",
+                    ),
+                ));
+            }
+        }
+    }
+    check_strays(dir, &expected, problems);
+}
+
+async fn check_walls(
+    dir: &Path,
+    tube_radius: f32,
+    tiling: RangeInclusive<f32>,
+    problems: &mut Vec<Problem>,
+) {
+    let mut expected = HashSet::new();
+    for name in load_list(dir, problems).await {
+        let path = dir.join(name);
+        expected.insert(path.clone());
+        let (width, height) = match image::image_dimensions(&path) {
+            Ok(size) => size,
+            Err(e) => {
+                problems.push(problem(&path, e));
+                continue;
+            }
+        };
+        if !width.is_power_of_two() || !height.is_power_of_two() {
+            problems.push(problem(
+                &path,
+                format_args!("{width}x{height} is not a power of two, can not be repeated on web"),
+            ));
+        }
+        // Same as the wall length in the game
+        let length = 2.0 * f32::PI * tube_radius / (width as f32 / height as f32);
+        if !tiling.contains(&length) {
+            problems.push(problem(
+                &path,
+                format_args!(
+                    "tiling length {length:.1} is outside of {:.1}..={:.1}",
+                    tiling.start(),
+                    tiling.end(),
+                ),
+            ));
+        }
+    }
+    check_strays(dir, &expected, problems);
+}
+
+/// Check obstacles and walls against files on disk without loading them to the GPU
+pub async fn check_all(overrides: &Overrides) -> Vec<Problem> {
+    let assets = run_dir().join("assets");
+    let mut problems = Vec::new();
+    let configs = difficulty::load_configs(overrides).await;
+    let render = overrides.load::<render::Config>(ConfigFile::Render).await;
+    let (Ok(configs), Ok(render)) = (configs, render) else {
+        problems.push(problem(
+            &assets,
+            "configs are invalid, run with --check-config for details",
+        ));
+        return problems;
+    };
+    let config = &configs[&difficulty::Difficulty::Normal];
+    // Obstacles must be passable on every difficulty
+    let player_radius = configs
+        .values()
+        .map(|config| config.player.radius)
+        .fold(0.0, f32::max);
+    check_obstacles(
+        &assets.join("obstacles"),
+        config.tube_radius,
+        player_radius,
+        &mut problems,
+    )
+    .await;
+    check_walls(
+        &assets.join("walls"),
+        config.tube_radius,
+        config.wall_section..=render.fog_distance,
+        &mut problems,
+    )
+    .await;
+    problems
+}
@@ -2,20 +2,21 @@ use geng_sprite_shape::ThickSprite;
 
 use super::*;
 
+use anyhow::Context as _;
+
 #[derive(geng::asset::Load)]
 pub struct Player {
     pub death: ugli::Texture,
     pub head: ugli::Texture,
     pub leg: ugli::Texture,
-    pub shadow: ugli::Texture,
 }
 
 #[derive(geng::asset::Load)]
 pub struct Tutorial {
-    pub wasd: ugli::Texture,
+    /// Blank key cap, the key name is drawn over it
+    pub key: ugli::Texture,
     pub arrows: ugli::Texture,
     pub touch: ugli::Texture,
-    pub r: ugli::Texture,
     pub touch_restart: ugli::Texture,
 }
 
@@ -42,17 +43,127 @@ pub struct Sfx {
     pub coin: geng::Sound,
 }
 
+fn default_weight() -> f32 {
+    1.0
+}
+
+fn default_rotation() -> config::MinMax<f32> {
+    config::MinMax {
+        min: 0.0,
+        max: 360.0,
+    }
+}
+
 #[derive(Deserialize)]
 pub struct ObstacleConfig {
     pub thickness: f32,
+    /// Relative chance to be chosen
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    /// How deep the player has to fall before this obstacle can appear
+    #[serde(default)]
+    pub min_depth: f32,
+    #[serde(default)]
+    pub max_depth: Option<f32>,
+    /// Number of following spawns this obstacle can not repeat in
+    #[serde(default)]
+    pub cooldown: usize,
+    /// Tilt in degrees, by default tilted just enough to cover the whole tube, either way
+    #[serde(default)]
+    pub tilt: Option<config::MinMax<f32>>,
+    /// Rotation around the tube axis in degrees
+    #[serde(default = "default_rotation")]
+    pub rotation: config::MinMax<f32>,
+}
+
+impl ObstacleConfig {
+    pub fn allowed_at(&self, depth: f32) -> bool {
+        depth >= self.min_depth && self.max_depth.map_or(true, |max| depth <= max)
+    }
+}
+
+impl validation::Validate for ObstacleConfig {
+    fn validate(&self, v: &mut validation::Validator) {
+        v.positive("thickness", self.thickness);
+        v.non_negative("weight", self.weight);
+        v.non_negative("min_depth", self.min_depth);
+        if let Some(max_depth) = self.max_depth {
+            v.check(
+                "max_depth",
+                max_depth >= self.min_depth,
+                format_args!(
+                    "must not be less than min_depth ({max_depth} < {})",
+                    self.min_depth,
+                ),
+            );
+        }
+        if let Some(tilt) = &self.tilt {
+            v.nested("tilt", tilt);
+            v.check(
+                "tilt",
+                tilt.min > -90.0 && tilt.max < 90.0,
+                "must be between -90 and 90 degrees",
+            );
+        }
+        v.nested("rotation", &self.rotation);
+    }
 }
 
 pub struct Obstacle {
-    pub config: ObstacleConfig,
+    path: std::path::PathBuf,
+    /// Name of the obstacle pack, none for the builtin obstacles
+    pub pack: Option<String>,
+    /// Can be replaced when hot reloading configs
+    pub config: RefCell<ObstacleConfig>,
     pub sprite: ThickSprite<render::Vertex>,
     pub data: Vec<Vec<Rgba<u8>>>,
 }
 
+impl Obstacle {
+    pub fn config_path(&self) -> std::path::PathBuf {
+        self.path.with_extension("toml")
+    }
+
+    /// Tilt that makes the obstacle cover the whole tube
+    pub fn cover_tilt(texture_size: vec2<usize>) -> Angle<f32> {
+        let aspect = texture_size.map(|x| x as f32).aspect();
+        Angle::from_radians(partial_min(aspect, 1.0 / aspect).acos())
+    }
+
+    pub fn random_tilt(&self, rng: &mut impl Rng) -> Angle<f32> {
+        match &self.config.borrow().tilt {
+            Some(tilt) => Angle::from_degrees(rng.gen_range(tilt.range())),
+            None => {
+                let tilt = Self::cover_tilt(self.sprite.texture.size());
+                if rng.gen() {
+                    Angle::from_radians(-tilt.as_radians())
+                } else {
+                    tilt
+                }
+            }
+        }
+    }
+
+    /// Placement in the tube before rotating around its axis.
+    ///
+    /// Local coordinates in `-1..1` cover the texture
+    pub fn base_transform(
+        texture_size: vec2<usize>,
+        thickness: f32,
+        tube_radius: f32,
+        tilt: Angle<f32>,
+    ) -> mat4<f32> {
+        let mut aspect = texture_size.map(|x| x as f32).aspect();
+        let mut transform = mat4::scale(vec3(1.0 / aspect, 1.0, thickness));
+        if aspect >= 1.0 {
+            // transform *= mat4::rotate_z(Angle::from_degrees(90.0));
+            aspect = 1.0 / aspect;
+        }
+        transform = mat4::scale((vec2(1.0, 1.0 / aspect) * tube_radius).extend(1.0)) * transform;
+        mat4::rotate_x(tilt) * transform
+    }
+}
+
 impl geng::asset::Load for Obstacle {
     fn load(
         manager: &geng::asset::Manager,
@@ -72,7 +183,7 @@ impl geng::asset::Load for Obstacle {
                     },
                 )
                 .await?;
-            let config: ObstacleConfig = file::load_detect(path.with_extension("toml")).await?;
+            let config: ObstacleConfig = validation::load(path.with_extension("toml")).await?;
             let original_texture: ugli::Texture = manager.load(path.with_extension("png")).await?;
             let fb = ugli::FramebufferRead::new_color(
                 manager.ugli(),
@@ -87,7 +198,9 @@ impl geng::asset::Load for Obstacle {
                 })
                 .collect();
             Ok(Self {
-                config,
+                path,
+                pack: None,
+                config: RefCell::new(config),
                 sprite,
                 data,
             })
@@ -98,6 +211,63 @@ impl geng::asset::Load for Obstacle {
     const DEFAULT_EXT: Option<&'static str> = Some("png");
 }
 
+async fn load_obstacle_pack(
+    manager: &geng::asset::Manager,
+    dir: &std::path::Path,
+    name: &str,
+) -> anyhow::Result<Vec<Rc<Obstacle>>> {
+    let list: Vec<String> = file::load_detect(dir.join("_list.ron")).await?;
+    let mut obstacles = Vec::new();
+    for item in list {
+        let mut obstacle: Obstacle = manager
+            .load(dir.join(&item))
+            .await
+            .with_context(|| format!("failed to load {item:?}"))?;
+        obstacle.pack = Some(name.to_owned());
+        obstacles.push(Rc::new(obstacle));
+    }
+    Ok(obstacles)
+}
+
+/// Every subdirectory is an obstacle pack laid out same as the builtin obstacles directory.
+///
+/// Broken packs are skipped
+pub async fn load_obstacle_packs(
+    manager: &geng::asset::Manager,
+    dir: &std::path::Path,
+) -> Vec<Rc<Obstacle>> {
+    if !dir.is_dir() {
+        return Vec::new();
+    }
+    let mut packs: Vec<_> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect(),
+        Err(e) => {
+            log::error!("failed to read obstacle packs from {dir:?}: {e}");
+            return Vec::new();
+        }
+    };
+    packs.sort();
+    let mut obstacles = Vec::new();
+    for path in packs {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        match load_obstacle_pack(manager, &path, &name).await {
+            Ok(pack) => {
+                log::info!(
+                    "loaded obstacle pack {name:?} with {} obstacles",
+                    pack.len()
+                );
+                obstacles.extend(pack);
+            }
+            Err(e) => log::error!("failed to load obstacle pack {name:?}: {e:#}"),
+        }
+    }
+    obstacles
+}
+
 #[derive(geng::asset::Load)]
 pub struct Assets {
     #[load(listed_in = "_list.ron")]
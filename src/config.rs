@@ -1,5 +1,8 @@
 use super::*;
 
+use std::collections::HashMap;
+use validation::{Validate, Validator};
+
 #[derive(Deserialize)]
 pub struct Camera {
     pub acceleration: f32,
@@ -29,13 +32,7 @@ pub struct Player {
 
     pub keyboard_control: PlayerControl,
     pub touch_control: PlayerControl,
-}
-
-#[derive(Deserialize)]
-pub struct Shadow {
-    pub alpha: f32,
-    pub distance: f32,
-    pub scale: f32,
+    pub mouse_control: PlayerControl,
 }
 
 #[derive(Deserialize)]
@@ -44,6 +41,23 @@ pub struct TouchControl {
     pub big_radius: f32,
 }
 
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CursorMode {
+    /// Regular system cursor, the player steers towards where it was last seen in the window
+    Free,
+    /// Cursor is locked and hidden, a virtual cursor confined to the window is used instead
+    Lock,
+}
+
+#[derive(Deserialize)]
+pub struct MouseControl {
+    /// Distance to the cursor at which the player moves at full speed
+    pub radius: f32,
+    pub cursor: CursorMode,
+    pub cursor_size: f32,
+}
+
 #[derive(Deserialize)]
 pub struct PassiveRotation {
     pub speed: f32,
@@ -84,15 +98,26 @@ pub struct Legs {
     pub rotate_speed: f32,
 }
 
+/// Control hints on the start and finish screens, in ui coordinates like the rest of the text
 #[derive(Deserialize)]
 pub struct Tutorial {
-    pub scale: f32,
-    pub final_scale: f32,
-    pub touch_pos: vec2<f32>,
+    /// Height of a key cap, the arrow keys are two keys high
+    pub key_size: f32,
+    /// Height of the touch gestures
+    pub touch_size: f32,
+    /// Center of the movement keys
     pub wasd_pos: vec2<f32>,
     pub arrows_pos: vec2<f32>,
-    pub touch_restart_pos: vec2<f32>,
-    pub r_pos: vec2<f32>,
+    pub touch_pos: vec2<f32>,
+    /// Restart key or gesture
+    pub restart_pos: vec2<f32>,
+    /// Hint under the control icons
+    pub text_pos: vec2<f32>,
+    /// Hint under the restart icon
+    pub restart_text_pos: vec2<f32>,
+    pub text_size: f32,
+    /// Key names on the key caps
+    pub key_text_size: f32,
 }
 
 #[derive(Deserialize)]
@@ -140,9 +165,134 @@ pub struct MoneyConfig {
     pub icon_offset: vec2<f32>,
 }
 
+/// Shown on the start screen, switches difficulty when clicked
+#[derive(Deserialize)]
+pub struct DifficultyButton {
+    pub pos: vec2<f32>,
+    pub size: vec2<f32>,
+    pub text_size: f32,
+}
+
+/// Top entries on the finish screen, on platforms with leaderboards
+#[derive(Deserialize)]
+pub struct Leaderboard {
+    /// Leaderboard in the SDK the best score is submitted to, usually one per difficulty
+    pub name: String,
+    pub pos: vec2<f32>,
+    pub line_height: f32,
+    pub text_size: f32,
+    pub top: u32,
+    /// Entries above and below the player
+    pub around: u32,
+}
+
+/// Yandex Games SDK, only with the yandex feature
+#[derive(Deserialize)]
+pub struct Sdk {
+    /// Seconds to wait for the SDK and the player to load before running without it,
+    /// optional features like payments are skipped if still loading by then
+    pub init_timeout: f32,
+    /// Seconds before a cloud save counts as failed and is retried later
+    pub save_timeout: f32,
+}
+
+/// When fullscreen ads are shown after a run
+#[derive(Deserialize)]
+pub struct Ads {
+    /// Seconds since the last ad
+    pub min_interval: f32,
+    /// Finished runs since the last ad
+    pub runs_between: usize,
+    /// Shorter runs in seconds never end with an ad
+    pub min_run_time: f32,
+    /// Games a new player finishes before seeing the first ad
+    pub grace_games: u64,
+}
+
+/// Button on the finish screen doubling the coins of the run for watching an ad
+#[derive(Deserialize)]
+pub struct RewardedAd {
+    pub pos: vec2<f32>,
+    pub size: vec2<f32>,
+    pub text_size: f32,
+}
+
+/// What buying a product gives
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShopReward {
+    Coins(u64),
+    /// Id of a cosmetic item added to the unlocks
+    Unlock(String),
+}
+
+/// In-app purchases listed on the start screen, on platforms with payments
+#[derive(Deserialize)]
+pub struct Shop {
+    pub pos: vec2<f32>,
+    pub line_height: f32,
+    pub width: f32,
+    pub text_size: f32,
+    /// Rewards by product id, catalog products missing here are not shown
+    pub products: HashMap<String, ShopReward>,
+}
+
+/// Progress code export and import on the start screen
+#[derive(Deserialize)]
+pub struct Share {
+    pub export_pos: vec2<f32>,
+    pub import_pos: vec2<f32>,
+    pub button_size: vec2<f32>,
+    pub text_size: f32,
+    /// Import confirmation and errors, one line after another
+    pub message_pos: vec2<f32>,
+    pub line_height: f32,
+    pub yes_pos: vec2<f32>,
+    pub no_pos: vec2<f32>,
+}
+
+/// Lifetime stats on the finish screen, one line after another
+#[derive(Deserialize)]
+pub struct Stats {
+    pub pos: vec2<f32>,
+    pub line_height: f32,
+    pub text_size: f32,
+}
+
+/// Opened with the back button or when the app is suspended mid-run
+#[derive(Deserialize)]
+pub struct PauseMenu {
+    pub overlay_alpha: f32,
+    pub title_pos: vec2<f32>,
+    pub resume_pos: vec2<f32>,
+    /// Not shown in browsers
+    pub quit_pos: vec2<f32>,
+    pub button_size: vec2<f32>,
+    pub text_size: f32,
+}
+
+/// List of obstacle packs on the start screen, each line toggles a pack
+#[derive(Deserialize)]
+pub struct ObstaclePacks {
+    pub pos: vec2<f32>,
+    pub line_height: f32,
+    pub width: f32,
+    pub text_size: f32,
+}
+
 #[derive(Deserialize)]
 pub struct Config {
     pub money: MoneyConfig,
+    pub difficulty_button: DifficultyButton,
+    pub share: Share,
+    pub sdk: Sdk,
+    pub leaderboard: Leaderboard,
+    pub ads: Ads,
+    pub rewarded_ad: RewardedAd,
+    pub shop: Shop,
+    pub stats: Stats,
+    pub pause_menu: PauseMenu,
+    pub obstacle_packs: ObstaclePacks,
     pub coin: Coin,
     pub digit_size: f32,
     pub score: Score,
@@ -151,6 +301,10 @@ pub struct Config {
     pub tutorial: Tutorial,
     pub start_time: f32,
     pub finish_time: f32,
+    /// Longer frames are simulated as this many seconds, like the first one after the app resumes
+    pub max_delta_time: f32,
+    /// Longer frames mid-run pause the game until any input instead of being simulated
+    pub pause_delta_time: f32,
     pub death_distance: f32,
     pub legs: Legs,
     pub shake: Shake,
@@ -159,9 +313,264 @@ pub struct Config {
     pub tube_radius: f32,
     pub camera: Camera,
     pub wall_section: f32,
-    pub shadow: Shadow,
     pub player: Player,
     pub passive_rotation: PassiveRotation,
     pub touch_control: TouchControl,
+    pub mouse_control: MouseControl,
     pub obstacles: Obstacles,
 }
+
+impl Validate for Camera {
+    fn validate(&self, v: &mut Validator) {
+        v.non_negative("acceleration", self.acceleration);
+        v.positive("distance", self.distance);
+        for (key, fov) in [("start_fov", self.start_fov), ("fov", self.fov)] {
+            v.check(
+                key,
+                fov > 0.0 && fov < 180.0,
+                format_args!("must be between 0 and 180 degrees, got {fov}"),
+            );
+        }
+    }
+}
+
+impl Validate for PlayerControl {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("max_speed", self.max_speed);
+        v.positive("acceleration", self.acceleration);
+    }
+}
+
+impl Validate for Player {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("fall_speed", self.fall_speed);
+        v.non_negative("fall_acceleration", self.fall_acceleration);
+        v.non_negative("fall_slow_acceleration", self.fall_slow_acceleration);
+        v.positive("radius", self.radius);
+        v.non_negative("bounce_speed", self.bounce_speed);
+        v.positive("bounce_time", self.bounce_time);
+        v.positive("death_radius", self.death_radius);
+        v.nested("keyboard_control", &self.keyboard_control);
+        v.nested("touch_control", &self.touch_control);
+        v.nested("mouse_control", &self.mouse_control);
+    }
+}
+
+impl Validate for TouchControl {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("small_radius", self.small_radius);
+        v.check(
+            "big_radius",
+            self.big_radius >= self.small_radius,
+            format_args!(
+                "must not be less than small_radius ({} < {})",
+                self.big_radius, self.small_radius,
+            ),
+        );
+    }
+}
+
+impl Validate for MouseControl {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("radius", self.radius);
+        v.non_negative("cursor_size", self.cursor_size);
+    }
+}
+
+impl Validate for Shake {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("time", self.time);
+        v.non_negative("amount", self.amount);
+        v.positive("freq", self.freq);
+    }
+}
+
+impl Validate for Obstacles {
+    fn validate(&self, v: &mut Validator) {
+        v.nested("distance", &self.distance);
+        v.positive("distance.min", self.distance.min);
+    }
+}
+
+impl Validate for Music {
+    fn validate(&self, v: &mut Validator) {
+        v.non_negative("fade_time", self.fade_time);
+    }
+}
+
+impl Validate for Score {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("fov", self.fov);
+        v.non_negative("digit_update_speed", self.digit_update_speed);
+    }
+}
+
+impl Validate for Coin {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("radius", self.radius);
+    }
+}
+
+impl Validate for DifficultyButton {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("size.x", self.size.x);
+        v.positive("size.y", self.size.y);
+        v.positive("text_size", self.text_size);
+    }
+}
+
+impl Validate for Leaderboard {
+    fn validate(&self, v: &mut Validator) {
+        v.check("name", !self.name.is_empty(), "must not be empty");
+        v.positive("line_height", self.line_height);
+        v.positive("text_size", self.text_size);
+        v.check(
+            "top",
+            (1..=20).contains(&self.top),
+            format_args!("must be from 1 to 20, got {}", self.top),
+        );
+        v.check(
+            "around",
+            self.around <= 10,
+            format_args!("must be at most 10, got {}", self.around),
+        );
+    }
+}
+
+impl Validate for Sdk {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("init_timeout", self.init_timeout);
+        v.positive("save_timeout", self.save_timeout);
+    }
+}
+
+impl Validate for Ads {
+    fn validate(&self, v: &mut Validator) {
+        v.non_negative("min_interval", self.min_interval);
+        v.check(
+            "runs_between",
+            self.runs_between >= 1,
+            format_args!("must be at least 1, got {}", self.runs_between),
+        );
+        v.non_negative("min_run_time", self.min_run_time);
+    }
+}
+
+impl Validate for RewardedAd {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("size.x", self.size.x);
+        v.positive("size.y", self.size.y);
+        v.positive("text_size", self.text_size);
+    }
+}
+
+impl Validate for Shop {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("line_height", self.line_height);
+        v.positive("width", self.width);
+        v.positive("text_size", self.text_size);
+        for (id, reward) in &self.products {
+            let key = format!("products.{id}");
+            match reward {
+                ShopReward::Coins(coins) => v.positive(&key, *coins),
+                ShopReward::Unlock(item) => {
+                    v.check(&key, !item.is_empty(), "unlock id must not be empty");
+                }
+            }
+        }
+    }
+}
+
+impl Validate for Share {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("button_size.x", self.button_size.x);
+        v.positive("button_size.y", self.button_size.y);
+        v.positive("text_size", self.text_size);
+        v.positive("line_height", self.line_height);
+    }
+}
+
+impl Validate for Stats {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("line_height", self.line_height);
+        v.positive("text_size", self.text_size);
+    }
+}
+
+impl Validate for PauseMenu {
+    fn validate(&self, v: &mut Validator) {
+        v.check(
+            "overlay_alpha",
+            (0.0..=1.0).contains(&self.overlay_alpha),
+            format_args!("must be from 0 to 1, got {}", self.overlay_alpha),
+        );
+        v.positive("button_size.x", self.button_size.x);
+        v.positive("button_size.y", self.button_size.y);
+        v.positive("text_size", self.text_size);
+    }
+}
+
+impl Validate for Tutorial {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("key_size", self.key_size);
+        v.positive("touch_size", self.touch_size);
+        v.positive("text_size", self.text_size);
+        v.positive("key_text_size", self.key_text_size);
+    }
+}
+
+impl Validate for ObstaclePacks {
+    fn validate(&self, v: &mut Validator) {
+        v.positive("line_height", self.line_height);
+        v.positive("width", self.width);
+        v.positive("text_size", self.text_size);
+    }
+}
+
+impl Validate for Config {
+    fn validate(&self, v: &mut Validator) {
+        v.nested("difficulty_button", &self.difficulty_button);
+        v.nested("share", &self.share);
+        v.nested("sdk", &self.sdk);
+        v.nested("leaderboard", &self.leaderboard);
+        v.nested("ads", &self.ads);
+        v.nested("rewarded_ad", &self.rewarded_ad);
+        v.nested("shop", &self.shop);
+        v.nested("stats", &self.stats);
+        v.nested("pause_menu", &self.pause_menu);
+        v.nested("obstacle_packs", &self.obstacle_packs);
+        v.positive("digit_size", self.digit_size);
+        v.positive("start_time", self.start_time);
+        v.positive("finish_time", self.finish_time);
+        v.positive("max_delta_time", self.max_delta_time);
+        v.check(
+            "pause_delta_time",
+            self.pause_delta_time >= self.max_delta_time,
+            format_args!(
+                "must not be less than max_delta_time ({} < {})",
+                self.pause_delta_time, self.max_delta_time,
+            ),
+        );
+        v.non_negative("death_distance", self.death_distance);
+        v.positive("tube_radius", self.tube_radius);
+        v.positive("wall_section", self.wall_section);
+        v.check(
+            "player.radius",
+            self.player.radius < self.tube_radius,
+            format_args!(
+                "must be less than tube_radius ({} >= {})",
+                self.player.radius, self.tube_radius,
+            ),
+        );
+        v.nested("coin", &self.coin);
+        v.nested("score", &self.score);
+        v.nested("music", &self.music);
+        v.nested("tutorial", &self.tutorial);
+        v.nested("shake", &self.shake);
+        v.nested("camera", &self.camera);
+        v.nested("player", &self.player);
+        v.nested("touch_control", &self.touch_control);
+        v.nested("mouse_control", &self.mouse_control);
+        v.nested("obstacles", &self.obstacles);
+    }
+}
@@ -11,6 +11,22 @@ pub struct Player {
 #[derive(Deserialize)]
 pub struct Controls {
     pub restart: Vec<geng::Key>,
+    /// Opens the pause menu, pressed again quits from it.
+    /// The Android back button comes as `BrowserBack`
     pub quit: Vec<geng::Key>,
+    pub difficulty: Vec<geng::Key>,
     pub player: Player,
 }
+
+impl validation::Validate for Controls {
+    fn validate(&self, v: &mut validation::Validator) {
+        for (key, keys) in [
+            ("player.up", &self.player.up),
+            ("player.left", &self.player.left),
+            ("player.down", &self.player.down),
+            ("player.right", &self.player.right),
+        ] {
+            v.check(key, !keys.is_empty(), "no keys bound");
+        }
+    }
+}
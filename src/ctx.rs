@@ -1,79 +1,150 @@
 use super::*;
 
+use difficulty::Difficulty;
+use overrides::{ConfigFile, Overrides};
+use std::collections::HashMap;
+
 #[derive(Clone, Deref)]
 pub struct Ctx {
     #[deref]
     inner: Rc<CtxInner>,
 }
 
-#[cfg(feature = "yandex")]
-pub struct Yandex {
-    pub sdk: ysdk::Ysdk,
-    pub player: ysdk::Player,
-}
-
+#[derive(Clone)]
 pub struct CtxInner {
     pub geng: Geng,
-    pub assets: assets::Assets,
-    pub config: config::Config,
+    pub assets: Rc<assets::Assets>,
+    /// Config for the current difficulty
+    pub config: Rc<config::Config>,
+    pub difficulty: Difficulty,
+    configs: Rc<HashMap<Difficulty, Rc<config::Config>>>,
     pub render: render::Render,
     pub particles: particles::Particles,
-    pub controls: controls::Controls,
-    #[cfg(feature = "yandex")]
-    pub yandex: Yandex,
+    pub controls: Rc<controls::Controls>,
+    pub overrides: Rc<Overrides>,
+    pub storage: Rc<storage::Storage>,
+    pub ads: Rc<ads::Ads>,
+    pub platform: Rc<dyn platform::Platform>,
+    pub locale: Rc<locale::Locale>,
     pub mobile: bool,
 }
 
 impl Ctx {
     pub async fn load(args: CliArgs, geng: &Geng) -> Self {
-        let config: config::Config =
-            file::load_detect(run_dir().join("assets").join("config.toml"))
-                .await
-                .unwrap();
-        let controls = file::load_detect(run_dir().join("assets").join("controls.toml"))
+        let overrides = Overrides::new(args.config_overlay.as_deref(), &args.set)
             .await
-            .unwrap();
-        let assets: assets::Assets = geng
+            .unwrap_or_else(|e| panic!("{e:#}"));
+        let configs = difficulty::load_configs(&overrides)
+            .await
+            .unwrap_or_else(|e| panic!("{e:#}"));
+        // Switched to the saved one once the save is loaded
+        let difficulty = Difficulty::default();
+        let controls = validation::load(run_dir().join("assets").join("controls.toml"))
+            .await
+            .unwrap_or_else(|e| panic!("{e}"));
+        let mut assets: assets::Assets = geng
             .asset_manager()
             .load(run_dir().join("assets"))
             .await
             .unwrap();
-        let render = render::Render::init(geng).await;
-        let particles = particles::Particles::init(geng).await;
-        #[cfg(feature = "yandex")]
-        let yandex = {
-            let sdk = ysdk::Ysdk::init().await.expect("Failed to initialize ysdk");
-            let player = sdk.player(false).await.unwrap();
-            Yandex { sdk, player }
-        };
+        let packs_dir = args
+            .obstacle_packs
+            .clone()
+            .unwrap_or_else(|| run_dir().join("obstacle_packs"));
+        assets
+            .obstacles
+            .extend(assets::load_obstacle_packs(geng.asset_manager(), &packs_dir).await);
+        let render = render::Render::init(geng, &overrides, configs[&difficulty].tube_radius).await;
+        let particles = particles::Particles::init(geng, &overrides).await;
+        let platform = platform::init(&configs[&difficulty]).await;
+        let language = args.language.clone().or_else(|| platform.language());
+        let locale = locale::Locale::load(language.as_deref()).await;
+        log::info!(
+            "running on {}, language {:?}, showing {}",
+            platform.name(),
+            language,
+            locale.language(),
+        );
+        let mut backends: Vec<Box<dyn storage::Backend>> = Vec::new();
+        if args.memory_save {
+            backends.push(Box::new(storage::Memory::default()));
+        } else {
+            backends.push(Box::new(storage::Local));
+            backends.extend(platform.cloud_storage());
+        }
         Self {
             inner: Rc::new(CtxInner {
                 geng: geng.clone(),
-                assets,
-                config,
-                controls,
+                assets: Rc::new(assets),
+                config: configs[&difficulty].clone(),
+                difficulty,
+                configs: Rc::new(configs),
+                controls: Rc::new(controls),
+                overrides: Rc::new(overrides),
                 render,
                 particles,
-                mobile: args.mobile.unwrap_or_else(|| {
-                    cfg_if::cfg_if! {
-                        if #[cfg(feature = "yandex")] {
-                            yandex.sdk.device_info().is_mobile()
-                        } else if #[cfg(target_arch = "wasm32")] {
-                            use wasm_bindgen::prelude::*;
-                            #[wasm_bindgen(module = "/src/detectmobilebrowser.js")]
-                            extern "C" {
-                                fn is_mobile_or_tablet() -> bool;
-                            }
-                            is_mobile_or_tablet()
-                        } else {
-                            cfg!(target_os = "android")
-                                || cfg!(target_os = "ios")
-                        }
-                    }
-                }),
-                #[cfg(feature = "yandex")]
-                yandex,
+                mobile: args
+                    .mobile
+                    .unwrap_or_else(|| platform.device() == platform::Device::Mobile),
+                storage: Rc::new(storage::Storage::new(backends)),
+                ads: Rc::new(ads::Ads::new(platform.ads())),
+                platform,
+                locale: Rc::new(locale),
             }),
         }
     }
+
+    /// Config files that can be reloaded while the game is running
+    pub fn config_files(&self) -> Vec<std::path::PathBuf> {
+        let mut files: Vec<_> = ConfigFile::ALL.into_iter().map(ConfigFile::path).collect();
+        files.push(difficulty::presets_path());
+        files.extend(
+            self.assets
+                .obstacles
+                .iter()
+                .map(|obstacle| obstacle.config_path()),
+        );
+        files
+    }
+
+    /// Parse all the configs again, sharing everything else with the current context.
+    ///
+    /// Nothing is changed if any of the files fail to parse.
+    pub async fn reload(&self) -> anyhow::Result<Self> {
+        let configs = difficulty::load_configs(&self.overrides).await?;
+        let render = self
+            .render
+            .reload(
+                &self.geng,
+                &self.overrides,
+                configs[&self.difficulty].tube_radius,
+            )
+            .await?;
+        let particles = self.particles.reload(&self.overrides).await?;
+        let mut obstacle_configs: Vec<assets::ObstacleConfig> = Vec::new();
+        for obstacle in &self.assets.obstacles {
+            obstacle_configs.push(validation::load(obstacle.config_path()).await?);
+        }
+        for (obstacle, config) in self.assets.obstacles.iter().zip(obstacle_configs) {
+            *obstacle.config.borrow_mut() = config;
+        }
+        let mut inner = CtxInner::clone(&self.inner);
+        inner.config = configs[&self.difficulty].clone();
+        inner.configs = Rc::new(configs);
+        inner.render = render;
+        inner.particles = particles;
+        Ok(Self {
+            inner: Rc::new(inner),
+        })
+    }
+
+    pub fn with_difficulty(&self, difficulty: Difficulty) -> Self {
+        let mut inner = CtxInner::clone(&self.inner);
+        inner.config = self.configs[&difficulty].clone();
+        inner.difficulty = difficulty;
+        inner.render.tube_radius = inner.config.tube_radius;
+        Self {
+            inner: Rc::new(inner),
+        }
+    }
 }
@@ -0,0 +1,84 @@
+use super::*;
+
+use overrides::Overrides;
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Relaxed,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Self; 3] = [Self::Relaxed, Self::Normal, Self::Hard];
+
+    /// Table name in difficulty.toml
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Relaxed => "relaxed",
+            Self::Normal => "normal",
+            Self::Hard => "hard",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Relaxed => Self::Normal,
+            Self::Normal => Self::Hard,
+            Self::Hard => Self::Relaxed,
+        }
+    }
+
+    /// Stat name for the best score, normal uses the key from before presets existed
+    pub fn best_score_key(self) -> &'static str {
+        match self {
+            Self::Relaxed => "best_score_relaxed",
+            Self::Normal => "best_score",
+            Self::Hard => "best_score_hard",
+        }
+    }
+
+    /// Name shown to the player
+    pub fn locale_key(self) -> &'static str {
+        match self {
+            Self::Relaxed => "difficulty_relaxed",
+            Self::Normal => "difficulty_normal",
+            Self::Hard => "difficulty_hard",
+        }
+    }
+}
+
+pub fn presets_path() -> std::path::PathBuf {
+    run_dir().join("assets").join("difficulty.toml")
+}
+
+/// Partial configs applied over config.toml, missing presets change nothing
+pub async fn load_presets() -> anyhow::Result<HashMap<Difficulty, toml::Table>> {
+    let mut presets: toml::Table = file::load_detect(presets_path()).await?;
+    let mut result = HashMap::new();
+    for difficulty in Difficulty::ALL {
+        let preset = match presets.remove(difficulty.name()) {
+            Some(toml::Value::Table(preset)) => preset,
+            Some(_) => anyhow::bail!("{:?} preset must be a table", difficulty.name()),
+            None => toml::Table::new(),
+        };
+        result.insert(difficulty, preset);
+    }
+    Ok(result)
+}
+
+/// Config for every difficulty
+pub async fn load_configs(
+    overrides: &Overrides,
+) -> anyhow::Result<HashMap<Difficulty, Rc<config::Config>>> {
+    let presets = load_presets().await?;
+    let mut result = HashMap::new();
+    for difficulty in Difficulty::ALL {
+        let config = overrides.load_config(&presets[&difficulty]).await?;
+        result.insert(difficulty, Rc::new(config));
+    }
+    Ok(result)
+}
@@ -28,6 +28,10 @@ impl FancyNumber {
         }
     }
 
+    pub fn set_ctx(&mut self, ctx: &Ctx) {
+        self.ctx = ctx.clone();
+    }
+
     pub fn set_value(&mut self, new_value: i32) {
         self.target_value = new_value;
     }
@@ -2,6 +2,8 @@ use self::{fancy_number::FancyNumber, particles::ParticleSpawner};
 
 use super::*;
 
+use std::collections::VecDeque;
+
 struct Player {
     pos: vec3<f32>,
     radius: f32,
@@ -59,6 +61,13 @@ struct TouchControl {
     prev_pos: vec2<f64>,
 }
 
+struct MouseControl {
+    /// Window position of the cursor (virtual one if the cursor is locked)
+    pos: vec2<f64>,
+    /// Moving the cursor does not start the run, so the start screen buttons can be reached
+    clicked: bool,
+}
+
 struct Bounce {
     t: f32,
     axis: vec3<f32>,
@@ -132,18 +141,20 @@ impl Ctx {
 /// Sarah is adorable
 pub struct GameState {
     key_input: bool,
-    best_score: i32,
+    save: save::SaveData,
     framebuffer_size: vec2<f32>,
     death_rotation: Angle<f32>,
     ctx: Ctx,
     time: f32,
     camera: Camera,
-    money: u64,
     player: Option<Player>,
     death_location: Option<vec3<f32>>,
     walls: Vec<Wall>,
     obstacles: Vec<Obstacle>,
+    /// Recently spawned obstacles for cooldowns, most recent last
+    obstacle_history: VecDeque<Rc<assets::Obstacle>>,
     touch_control: Option<TouchControl>,
+    mouse_control: Option<MouseControl>,
     bounce: Option<Bounce>,
     bounce_particles: ParticleSpawner,
     shake_time: f32,
@@ -157,23 +168,53 @@ pub struct GameState {
     money_fancy: FancyNumber,
     coins: Vec<vec3<f32>>,
     finish_ad_shown: bool,
+    /// Seconds the player has been falling
+    run_time: f32,
     need_restart: bool,
+    /// Set from event handlers, saved in the main loop
+    need_save: bool,
+    /// Saving or retrying a failed save, None if not in progress.
+    ///
+    /// Only one runs at a time, so an older save never lands after a newer one
+    saving: Option<future::LocalBoxFuture<'static, ()>>,
+    /// Imported progress waiting for confirmation
+    pending_import: Option<share::Shared>,
+    /// Result of the last export, import or purchase
+    share_message: Option<String>,
+    /// Shown on the finish screen
+    leaderboard: Vec<platform::LeaderboardEntry>,
+    /// Submitting the score and fetching [Self::leaderboard], None if not in progress
+    leaderboard_update:
+        Option<future::LocalBoxFuture<'static, Option<Vec<platform::LeaderboardEntry>>>>,
+    /// Coins collected this run, can be doubled by watching an ad
+    run_money: u64,
+    need_rewarded_ad: bool,
+    /// Product to buy
+    need_purchase: Option<String>,
+    /// Paused by the platform, like when the tab is hidden or an ad is shown
+    platform_paused: bool,
+    /// Window lost focus, like when the app went to the background
+    suspended: bool,
+    /// Opened with the back button, or on its own when suspended mid-run
+    menu_open: bool,
+    /// A frame took too long mid-run, like when the tab was hidden or the window dragged.
+    /// Play resumes on any input
+    gap_paused: bool,
+    need_quit: bool,
 }
 
 impl GameState {
-    pub async fn new(ctx: &Ctx) -> Self {
+    pub async fn new(ctx: &Ctx, save: save::SaveData) -> Self {
+        let ctx = &ctx.with_difficulty(save.settings.difficulty);
         let mut effect = ctx.assets.sfx.start.effect();
         effect.set_volume(ctx.config.sfx.start_volume);
         effect.play();
 
         Self {
-            #[cfg(feature = "yandex")]
-            money: ctx.yandex.player.numeric_data("money").await.unwrap_or(0.0) as u64,
-            #[cfg(not(feature = "yandex"))]
-            money: preferences::load("money").unwrap_or(0),
+            save,
             finish_ad_shown: false,
+            run_time: 0.0,
             coins: Vec::new(),
-            best_score: preferences::load("best_score").unwrap_or(0),
             score: 0.0,
             score_fancy: FancyNumber::new(ctx, 0),
             money_fancy: FancyNumber::new(ctx, 0),
@@ -181,12 +222,27 @@ impl GameState {
             finished: None,
             time: 0.0,
             obstacles: Vec::new(),
+            obstacle_history: VecDeque::new(),
             framebuffer_size: vec2::splat(1.0),
             death_location: None,
             wind: ctx.sound_effect(&ctx.assets.sfx.wind, 0.0),
             swim: ctx.sound_effect(&ctx.assets.sfx.swim, 0.0),
             music: ctx.start_music(&ctx.assets.music.piano),
             need_restart: false,
+            need_save: false,
+            saving: None,
+            pending_import: None,
+            share_message: None,
+            leaderboard: Vec::new(),
+            leaderboard_update: None,
+            run_money: 0,
+            need_rewarded_ad: false,
+            need_purchase: None,
+            platform_paused: false,
+            suspended: false,
+            menu_open: false,
+            gap_paused: false,
+            need_quit: false,
             camera: Camera {
                 pos: vec3::ZERO,
                 fov: Angle::from_degrees(ctx.config.camera.start_fov),
@@ -209,6 +265,7 @@ impl GameState {
             }),
             walls: Vec::new(),
             touch_control: None,
+            mouse_control: None,
             bounce: None,
             bounce_particles: ctx.particles.spawner(&ctx.particles.config.bounce),
             shake_time: 0.0,
@@ -222,18 +279,256 @@ impl GameState {
 
     fn key_press(&mut self, key: geng::Key) {
         self.key_input = true;
-        if self.ctx.controls.quit.contains(&key) && !cfg!(target_arch = "wasm32") {
-            // TODO: exit maybe potentially?
+        if self.ctx.controls.quit.contains(&key) {
+            if self.ctx.geng.window().cursor_locked() {
+                self.ctx.geng.window().unlock_cursor();
+            } else if !self.menu_open {
+                self.open_menu();
+            } else if self.ctx.platform.can_quit() {
+                self.need_quit = true;
+            } else {
+                self.close_menu();
+            }
+            return;
+        }
+        if self.menu_open {
+            return;
         }
         if self.ctx.controls.restart.contains(&key) {
             self.restart();
         }
+        if self.ctx.controls.difficulty.contains(&key) && self.started.is_none() {
+            self.switch_difficulty();
+        }
         if self.finished.unwrap_or(0.0) > 1.0 {
             self.restart();
         }
     }
 
+    fn ui_camera(&self) -> geng::Camera2d {
+        // Same view area as the camera in draw_ui
+        let aspect = self.framebuffer_size.aspect();
+        geng::Camera2d {
+            center: vec2::ZERO,
+            rotation: Angle::ZERO,
+            fov: if aspect > 1.0 {
+                self.ctx.config.score.fov
+            } else {
+                self.ctx.config.score.fov / aspect
+            },
+        }
+    }
+
+    /// Names of loaded obstacle packs in loading order
+    fn obstacle_packs(&self) -> Vec<String> {
+        let mut packs: Vec<String> = Vec::new();
+        for obstacle in &self.ctx.assets.obstacles {
+            if let Some(pack) = &obstacle.pack {
+                if !packs.contains(pack) {
+                    packs.push(pack.clone());
+                }
+            }
+        }
+        packs
+    }
+
+    fn obstacle_pack_line_pos(&self, index: usize) -> vec2<f32> {
+        let config = &self.ctx.config.obstacle_packs;
+        config.pos - vec2(0.0, config.line_height * index as f32)
+    }
+
+    /// Catalog products the config knows, in catalog order
+    fn shop_products(&self) -> Vec<&platform::Product> {
+        let Some(payments) = self.ctx.platform.payments() else {
+            return Vec::new();
+        };
+        payments
+            .catalog()
+            .iter()
+            .filter(|product| self.ctx.config.shop.products.contains_key(&product.id))
+            .collect()
+    }
+
+    fn shop_line_pos(&self, index: usize) -> vec2<f32> {
+        let config = &self.ctx.config.shop;
+        config.pos - vec2(0.0, config.line_height * index as f32)
+    }
+
+    /// Ask to confirm importing the progress from the code
+    pub fn offer_import(&mut self, code: &str) {
+        match share::Shared::import(code) {
+            Ok(shared) => {
+                self.pending_import = Some(shared);
+                self.share_message = None;
+            }
+            Err(e) => {
+                log::warn!("failed to import progress: {e}");
+                self.share_message = Some(self.ctx.locale.get(e.locale_key()).to_owned());
+            }
+        }
+    }
+
+    /// Handle clicks on the start screen buttons, returns true if something was clicked
+    fn start_screen_click(&mut self, window_pos: vec2<f64>) -> bool {
+        if self.started.is_some() {
+            return false;
+        }
+        let pos = self
+            .ui_camera()
+            .screen_to_world(self.framebuffer_size, window_pos.map(|x| x as f32));
+        let buttons = &self.ctx.config.share;
+        let hit = |center: vec2<f32>| {
+            Aabb2::point(center)
+                .extend_symmetric(buttons.button_size / 2.0)
+                .contains(pos)
+        };
+        if let Some(shared) = self.pending_import.take() {
+            if hit(buttons.yes_pos) {
+                shared.apply(&mut self.save);
+                self.need_save = true;
+                self.share_message = Some(self.ctx.locale.get("import_done").to_owned());
+            } else if !hit(buttons.no_pos) {
+                self.pending_import = Some(shared);
+            }
+            return true;
+        }
+        if let Some(prompt) = self.ctx.platform.code_prompt() {
+            if hit(buttons.export_pos) {
+                prompt.show_code(
+                    self.ctx.locale.get("export_prompt"),
+                    &share::Shared::new(&self.save).export(),
+                );
+                return true;
+            }
+            if hit(buttons.import_pos) {
+                if let Some(code) = prompt.ask_code(self.ctx.locale.get("import_prompt")) {
+                    self.offer_import(&code);
+                }
+                return true;
+            }
+        }
+        let config = &self.ctx.config.shop;
+        let clicked = self
+            .shop_products()
+            .into_iter()
+            .enumerate()
+            .find(|&(index, _)| {
+                Aabb2::point(self.shop_line_pos(index))
+                    .extend_symmetric(vec2(config.width, config.line_height) / 2.0)
+                    .contains(pos)
+            })
+            .map(|(_, product)| product.id.clone());
+        if let Some(id) = clicked {
+            if !shop::owned(config, &self.save, &id) {
+                self.need_purchase = Some(id);
+            }
+            return true;
+        }
+        let button = &self.ctx.config.difficulty_button;
+        if Aabb2::point(button.pos)
+            .extend_symmetric(button.size / 2.0)
+            .contains(pos)
+        {
+            self.switch_difficulty();
+            return true;
+        }
+        let config = &self.ctx.config.obstacle_packs;
+        for (index, pack) in self.obstacle_packs().into_iter().enumerate() {
+            if Aabb2::point(self.obstacle_pack_line_pos(index))
+                .extend_symmetric(vec2(config.width, config.line_height) / 2.0)
+                .contains(pos)
+            {
+                self.save.settings.toggle_obstacle_pack(&pack);
+                self.save.settings_updated_at = save::now();
+                self.need_save = true;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Weighted choice among enabled obstacles allowed at this depth and not on cooldown.
+    ///
+    /// Cooldowns and then depth limits are relaxed if nothing is left to choose from
+    fn choose_obstacle(&self, depth: f32) -> Rc<assets::Obstacle> {
+        let enabled: Vec<&Rc<assets::Obstacle>> = self
+            .ctx
+            .assets
+            .obstacles
+            .iter()
+            .filter(|obstacle| {
+                obstacle
+                    .pack
+                    .as_ref()
+                    .map_or(true, |pack| self.save.settings.obstacle_pack_enabled(pack))
+            })
+            .collect();
+        let on_cooldown = |obstacle: &Rc<assets::Obstacle>| {
+            let cooldown = obstacle.config.borrow().cooldown;
+            self.obstacle_history
+                .iter()
+                .rev()
+                .take(cooldown)
+                .any(|recent| Rc::ptr_eq(recent, obstacle))
+        };
+        let choose = |options: Vec<&Rc<assets::Obstacle>>| {
+            options
+                .choose_weighted(&mut thread_rng(), |obstacle| {
+                    obstacle.config.borrow().weight
+                })
+                .ok()
+                .map(|&obstacle| obstacle.clone())
+        };
+        let at_depth: Vec<_> = enabled
+            .iter()
+            .copied()
+            .filter(|obstacle| obstacle.config.borrow().allowed_at(depth))
+            .collect();
+        choose(
+            at_depth
+                .iter()
+                .copied()
+                .filter(|&obstacle| !on_cooldown(obstacle))
+                .collect(),
+        )
+        .or_else(|| choose(at_depth))
+        .or_else(|| choose(enabled.clone()))
+        .unwrap_or_else(|| (*enabled.choose(&mut thread_rng()).unwrap()).clone())
+    }
+
+    fn finish_screen_click(&mut self, window_pos: vec2<f64>) -> bool {
+        if self.finished.is_none() || self.run_money == 0 {
+            return false;
+        }
+        let pos = self
+            .ui_camera()
+            .screen_to_world(self.framebuffer_size, window_pos.map(|x| x as f32));
+        let button = &self.ctx.config.rewarded_ad;
+        if Aabb2::point(button.pos)
+            .extend_symmetric(button.size / 2.0)
+            .contains(pos)
+        {
+            self.need_rewarded_ad = true;
+            return true;
+        }
+        false
+    }
+
+    fn switch_difficulty(&mut self) {
+        let difficulty = self.ctx.difficulty.next();
+        self.save.settings.difficulty = difficulty;
+        self.save.settings_updated_at = save::now();
+        self.set_ctx(self.ctx.with_difficulty(difficulty));
+        self.need_save = true;
+    }
+
     fn touch_start(&mut self, pos: vec2<f64>) {
+        if self.start_screen_click(pos) {
+            return;
+        }
+        if self.finish_screen_click(pos) {
+            return;
+        }
         self.touch_control = Some(TouchControl {
             move_delta: vec2::ZERO,
             prev_pos: pos,
@@ -270,13 +565,220 @@ impl GameState {
         self.touch_control = None;
     }
 
+    fn mouse_press(&mut self) {
+        if let Some(pos) = self.ctx.geng.window().cursor_position() {
+            if self.start_screen_click(pos) {
+                return;
+            }
+            if self.finish_screen_click(pos) {
+                return;
+            }
+        }
+        let window = self.ctx.geng.window();
+        let pos = window
+            .cursor_position()
+            .unwrap_or(self.framebuffer_size.map(|x| x as f64) / 2.0);
+        self.mouse_control
+            .get_or_insert(MouseControl {
+                pos,
+                clicked: false,
+            })
+            .clicked = true;
+        if self.ctx.config.mouse_control.cursor == config::CursorMode::Lock
+            && !window.cursor_locked()
+        {
+            window.lock_cursor();
+        }
+        if self.finished.unwrap_or(0.0) > 1.0 {
+            self.restart();
+        }
+    }
+
+    fn mouse_move(&mut self, pos: vec2<f64>) {
+        if self.ctx.geng.window().cursor_locked() {
+            return;
+        }
+        self.mouse_control
+            .get_or_insert(MouseControl {
+                pos,
+                clicked: false,
+            })
+            .pos = pos;
+    }
+
+    fn mouse_raw_move(&mut self, delta: vec2<f64>) {
+        if !self.ctx.geng.window().cursor_locked() {
+            return;
+        }
+        let size = self.framebuffer_size.map(|x| x as f64);
+        if let Some(mouse) = &mut self.mouse_control {
+            let pos = mouse.pos + delta;
+            mouse.pos = vec2(pos.x.clamp(0.0, size.x), pos.y.clamp(0.0, size.y));
+        }
+    }
+
+    /// Switch to reloaded configs
+    fn set_ctx(&mut self, ctx: Ctx) {
+        self.camera.far = ctx.render.config.fog_distance;
+        self.score_fancy.set_ctx(&ctx);
+        self.money_fancy.set_ctx(&ctx);
+        self.bounce_particles = ctx.particles.spawner(&ctx.particles.config.bounce);
+        if let Some(player) = &mut self.player {
+            player.radius = ctx.config.player.radius;
+            player.move_particles = ctx.particles.spawner(&ctx.particles.config.movement);
+        }
+        self.ctx = ctx;
+    }
+
+    /// Nothing is simulated or heard while paused
+    fn paused(&self) -> bool {
+        self.platform_paused || self.suspended || self.menu_open || self.gap_paused
+    }
+
+    fn update_volume(&self) {
+        let volume = if self.paused() { 0.0 } else { 1.0 };
+        self.ctx.geng.audio().set_volume(volume);
+    }
+
+    /// Between the first move and death
+    fn in_run(&self) -> bool {
+        self.started.is_some() && self.finished.is_none()
+    }
+
+    fn platform_event(&mut self, event: platform::Event) {
+        self.platform_paused = match event {
+            platform::Event::Pause => true,
+            platform::Event::Resume => false,
+        };
+        if self.platform_paused {
+            // Releases during the pause are not seen
+            self.touch_control = None;
+            // Same as losing focus, the app may have gone to the background
+            if self.in_run() {
+                self.open_menu();
+            }
+        }
+        self.update_volume();
+    }
+
+    fn focus_changed(&mut self, focused: bool) {
+        self.suspended = !focused;
+        // Coming back mid-run should not throw the player right into the action
+        if self.suspended && self.in_run() {
+            self.open_menu();
+        }
+        self.update_volume();
+    }
+
+    fn open_menu(&mut self) {
+        if mem::replace(&mut self.menu_open, true) {
+            return;
+        }
+        // The finger is likely lifted by the time the menu is closed
+        self.touch_control = None;
+        // Gameplay was already stopped by the frame gap
+        let was_playing = !mem::take(&mut self.gap_paused);
+        if self.in_run() && was_playing {
+            self.ctx.platform.gameplay_stop();
+        }
+        self.update_volume();
+    }
+
+    /// Nothing happened during the gap, so it is treated like a pause instead of simulated
+    fn frame_gap(&mut self) {
+        self.gap_paused = true;
+        self.touch_control = None;
+        self.ctx.platform.gameplay_stop();
+        self.update_volume();
+    }
+
+    fn resume_after_gap(&mut self) {
+        self.gap_paused = false;
+        self.ctx.platform.gameplay_start();
+        self.update_volume();
+    }
+
+    fn close_menu(&mut self) {
+        if !mem::take(&mut self.menu_open) {
+            return;
+        }
+        if self.in_run() {
+            self.ctx.platform.gameplay_start();
+        }
+        self.update_volume();
+    }
+
+    fn menu_button_hit(&self, window_pos: vec2<f64>, center: vec2<f32>) -> bool {
+        let pos = self
+            .ui_camera()
+            .screen_to_world(self.framebuffer_size, window_pos.map(|x| x as f32));
+        Aabb2::point(center)
+            .extend_symmetric(self.ctx.config.pause_menu.button_size / 2.0)
+            .contains(pos)
+    }
+
+    fn menu_click(&mut self, window_pos: vec2<f64>) {
+        let config = &self.ctx.config.pause_menu;
+        if self.menu_button_hit(window_pos, config.resume_pos) {
+            self.close_menu();
+        } else if self.ctx.platform.can_quit() && self.menu_button_hit(window_pos, config.quit_pos)
+        {
+            self.need_quit = true;
+        }
+    }
+
+    /// Only the menu buttons and the back button work while the menu is open
+    fn menu_event(&mut self, event: geng::Event) {
+        match event {
+            geng::Event::KeyPress { key } => self.key_press(key),
+            geng::Event::MousePress { .. } => {
+                if let Some(pos) = self.ctx.geng.window().cursor_position() {
+                    self.menu_click(pos);
+                }
+            }
+            geng::Event::TouchStart(touch) => self.menu_click(touch.position),
+            _ => {}
+        }
+    }
+
     pub async fn run(mut self) {
-        let ctx = self.ctx.clone();
         let mut timer = Timer::new();
-        while let Some(event) = ctx.geng.window().events().next().await {
+        let mut hot_reload = cfg!(debug_assertions).then(|| hot_reload::HotReload::new(&self.ctx));
+        // Not a `while let`, the stream would keep `self.ctx` borrowed for the whole iteration
+        loop {
+            let Some(event) = self.ctx.geng.window().events().next().await else {
+                break;
+            };
             if let geng::Event::Draw = event {
-                self.update(timer.tick()).await;
-                geng::async_state::with_current_framebuffer(ctx.geng.window(), |framebuffer| {
+                // Once per frame, polling the platform may be slow like on Android
+                while let Some(event) = self.ctx.platform.next_event() {
+                    self.platform_event(event);
+                }
+                if hot_reload
+                    .as_mut()
+                    .map_or(false, |hot_reload| hot_reload.check())
+                {
+                    match self.ctx.reload().await {
+                        Ok(ctx) => {
+                            log::info!("configs reloaded");
+                            self.set_ctx(ctx);
+                        }
+                        Err(e) => log::error!("failed to reload configs: {e:?}"),
+                    }
+                }
+                // Ticked while paused too, so the time spent paused is never simulated
+                let delta_time = timer.tick().as_secs_f64() as f32;
+                if delta_time > self.ctx.config.pause_delta_time && self.in_run() && !self.paused()
+                {
+                    log::info!("paused after a {delta_time:.1}s frame");
+                    self.frame_gap();
+                }
+                if !self.paused() {
+                    self.update(delta_time.min(self.ctx.config.max_delta_time))
+                        .await;
+                }
+                let geng = self.ctx.geng.clone();
+                geng::async_state::with_current_framebuffer(geng.window(), |framebuffer| {
                     self.draw(framebuffer)
                 });
             } else {
@@ -284,17 +786,90 @@ impl GameState {
             }
             if self.finished.unwrap_or(0.0) > 1.0 && !mem::replace(&mut self.finish_ad_shown, true)
             {
-                #[cfg(feature = "yandex")]
+                let games_played = self.save.stats.games_played;
+                self.ctx.ads.run_finished();
+                if self
+                    .ctx
+                    .ads
+                    .should_show(&self.ctx.config.ads, self.run_time, games_played)
                 {
-                    ctx.geng.audio().set_volume(0.0);
-                    let result = ctx.yandex.sdk.show_fullscreen_adv().await;
-                    ctx.geng.audio().set_volume(1.0);
+                    self.ctx.geng.audio().set_volume(0.0);
+                    let result = self.ctx.ads.show_fullscreen().await;
+                    self.update_volume();
                     log::info!("showed ad: {:?}", result);
                 }
             }
+            if mem::take(&mut self.need_rewarded_ad) {
+                self.ctx.geng.audio().set_volume(0.0);
+                let result = self.ctx.ads.show_rewarded().await;
+                self.update_volume();
+                log::info!("showed rewarded video: {:?}", result);
+                if let Ok(true) = result {
+                    self.save.money += mem::take(&mut self.run_money);
+                    self.save.updated_at = save::now();
+                    self.need_save = true;
+                }
+            }
+            if let Some(id) = self.need_purchase.take() {
+                match shop::buy(&self.ctx, &mut self.save, &id).await {
+                    Ok(()) => {
+                        self.share_message = Some(self.ctx.locale.get("purchase_done").to_owned());
+                    }
+                    Err(platform::PurchaseError::Cancelled) => {}
+                    Err(e) => {
+                        log::error!("failed to buy {id}: {e}");
+                        self.share_message =
+                            Some(self.ctx.locale.format("purchase_failed", &[("error", &e)]));
+                    }
+                }
+            }
+            if let Some(result) = self
+                .leaderboard_update
+                .as_mut()
+                .and_then(|update| update.now_or_never())
+            {
+                self.leaderboard_update = None;
+                if let Some(entries) = result {
+                    self.leaderboard = entries;
+                }
+            }
+            if let Some(saving) = &mut self.saving {
+                if saving.now_or_never().is_some() {
+                    self.saving = None;
+                }
+            }
+            if self.saving.is_none() {
+                if mem::take(&mut self.need_save) {
+                    self.save_progress();
+                } else if self.ctx.storage.retry_due() {
+                    let storage = self.ctx.storage.clone();
+                    let save = self.save.clone();
+                    self.saving = Some(async move { storage.retry(&save).await }.boxed_local());
+                }
+            }
             if self.need_restart {
-                self = Self::new(&self.ctx).await;
+                if self.started.is_some() && self.finished.is_none() {
+                    self.ctx.platform.gameplay_stop();
+                }
+                let ctx = self.ctx.clone();
+                // The score is still submitted and saved if the player restarts right away
+                let leaderboard_update = self.leaderboard_update.take();
+                let saving = self.saving.take();
+                let need_save = self.need_save;
+                self = Self::new(&ctx, self.save).await;
+                self.leaderboard_update = leaderboard_update;
+                self.saving = saving;
+                self.need_save = need_save;
             }
+            if self.need_quit {
+                break;
+            }
+        }
+        if let Some(saving) = self.saving.take() {
+            saving.await;
+        }
+        if self.need_save {
+            self.ctx.storage.save(&self.save).await;
         }
     }
 
@@ -362,30 +937,6 @@ impl GameState {
         );
 
         if let Some(player) = &self.player {
-            #[cfg(feature = "never")]
-            {
-                // shadow
-                let distance_to_tube =
-                    self.ctx.config.tube_radius - player.pos.xy().len() - player.radius;
-                let shadow_k =
-                    (1.0 - distance_to_tube / self.ctx.config.shadow.distance).clamp(0.0, 1.0);
-                self.ctx.render.sprite_ext(
-                    framebuffer,
-                    &self.camera,
-                    &self.ctx.assets.player.shadow,
-                    mat4::translate(
-                        (player.pos.xy().normalize_or_zero() * self.ctx.config.tube_radius)
-                            .extend(player.pos.z),
-                    ) * mat4::rotate_z(player.pos.xy().arg())
-                        * mat4::rotate_y(Angle::from_degrees(-90.0))
-                        * mat4::scale_uniform(
-                            (1.0 - shadow_k) * self.ctx.config.shadow.scale + shadow_k,
-                        ),
-                    Rgba::new(1.0, 1.0, 1.0, self.ctx.config.shadow.alpha * shadow_k),
-                    false,
-                );
-            }
-
             let mut transform = mat4::translate(player.pos) * mat4::scale_uniform(player.radius);
             transform *= mat4::rotate_y(Angle::from_degrees(
                 self.ctx.config.player.rotate_angle * player.vel.x
@@ -445,6 +996,20 @@ impl GameState {
                 transform,
             );
 
+            if let Some(mouse) = &self.mouse_control {
+                if self.ctx.geng.window().cursor_locked() {
+                    self.ctx.render.sprite_ext(
+                        framebuffer,
+                        &self.camera,
+                        &self.ctx.render.white_texture,
+                        mat4::translate(self.raycast(mouse.pos).extend(player.pos.z))
+                            * mat4::scale_uniform(self.ctx.config.mouse_control.cursor_size),
+                        Rgba::WHITE,
+                        false,
+                    );
+                }
+            }
+
             if let Some(touch) = &self.touch_control {
                 self.ctx.render.sprite_ext(
                     framebuffer,
@@ -488,72 +1053,201 @@ impl GameState {
             );
         }
 
-        // tutorial
-        {
-            let alpha = (1.0 - self.started.unwrap_or(0.0)).clamp(0.0, 1.0);
-            let mobile_tutorial = [(
-                &self.ctx.assets.tutorial.touch,
-                self.ctx.config.tutorial.touch_pos,
-            )];
-            let desktop_tutorial = [
-                (
-                    &self.ctx.assets.tutorial.wasd,
-                    self.ctx.config.tutorial.wasd_pos,
-                ),
-                (
-                    &self.ctx.assets.tutorial.arrows,
-                    self.ctx.config.tutorial.arrows_pos,
-                ),
-            ];
-            let tutorial: &[_] = if self.ctx.mobile {
-                &mobile_tutorial
-            } else {
-                &desktop_tutorial
-            };
-            for (texture, pos) in tutorial {
-                // let mut pos = pos;
-                // if self.framebuffer_size.aspect() > 1.0 {
-                //     pos = pos.xy().rotate_90().extend(pos.z);
-                // }
-                self.ctx.render.sprite_ext(
-                    framebuffer,
-                    &self.camera,
-                    texture,
-                    mat4::translate(pos.extend(0.0))
-                        * mat4::scale(
-                            texture.size().map(|x| x as f32).extend(1.0)
-                                * self.ctx.config.tutorial.scale,
-                        ),
-                    Rgba::new(1.0, 1.0, 1.0, alpha),
-                    false,
-                );
+        self.draw_ui(framebuffer);
+    }
+
+    /// Control icons, fading out on start and in on finish
+    fn draw_tutorial(&self, framebuffer: &mut ugli::Framebuffer, camera: &dyn AbstractCamera3d) {
+        let config = &self.ctx.config.tutorial;
+        let assets = &self.ctx.assets.tutorial;
+        let controls = &self.ctx.controls;
+        let alpha = (1.0 - self.started.unwrap_or(0.0)).clamp(0.0, 1.0);
+        if self.ctx.mobile {
+            let (pos, size) = (config.touch_pos, config.touch_size);
+            self.draw_icon(framebuffer, camera, &assets.touch, pos, size, alpha);
+            self.draw_hint(framebuffer, "tutorial_touch", config.text_pos, alpha);
+        } else {
+            for (keys, offset) in [
+                (&controls.player.up, vec2(0.0, 0.5)),
+                (&controls.player.left, vec2(-1.0, -0.5)),
+                (&controls.player.down, vec2(0.0, -0.5)),
+                (&controls.player.right, vec2(1.0, -0.5)),
+            ] {
+                let pos = config.wasd_pos + offset * config.key_size;
+                self.draw_key(framebuffer, camera, keys, pos, alpha);
             }
+            let (pos, size) = (config.arrows_pos, config.key_size * 2.0);
+            self.draw_icon(framebuffer, camera, &assets.arrows, pos, size, alpha);
+            self.draw_hint(framebuffer, "tutorial_keys", config.text_pos, alpha);
         }
-        {
-            let alpha = self.finished.unwrap_or(0.0);
-            let (texture, pos) = if self.ctx.mobile {
-                (
-                    &self.ctx.assets.tutorial.touch_restart,
-                    self.ctx.config.tutorial.touch_restart_pos,
-                )
+
+        let alpha = self.finished.unwrap_or(0.0).min(1.0);
+        let hint = if self.ctx.mobile {
+            let (pos, size) = (config.restart_pos, config.touch_size);
+            self.draw_icon(framebuffer, camera, &assets.touch_restart, pos, size, alpha);
+            "tutorial_restart_touch"
+        } else {
+            self.draw_key(
+                framebuffer,
+                camera,
+                &controls.restart,
+                config.restart_pos,
+                alpha,
+            );
+            "tutorial_restart_key"
+        };
+        self.draw_hint(framebuffer, hint, config.restart_text_pos, alpha);
+    }
+
+    /// Texture of the given height centered at the ui position
+    fn draw_icon(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &dyn AbstractCamera3d,
+        texture: &ugli::Texture,
+        pos: vec2<f32>,
+        height: f32,
+        alpha: f32,
+    ) {
+        let size = vec2(texture.size().map(|x| x as f32).aspect(), 1.0) * height;
+        self.ctx.render.sprite_ext(
+            framebuffer,
+            camera,
+            texture,
+            mat4::translate(pos.extend(0.0)) * mat4::scale((size / 2.0).extend(1.0)),
+            Rgba::new(1.0, 1.0, 1.0, alpha),
+            false,
+        );
+    }
+
+    /// Key cap with the name of the first bound key, so it matches controls.toml
+    fn draw_key(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &dyn AbstractCamera3d,
+        keys: &[geng::Key],
+        pos: vec2<f32>,
+        alpha: f32,
+    ) {
+        let config = &self.ctx.config.tutorial;
+        let texture = &self.ctx.assets.tutorial.key;
+        self.draw_icon(framebuffer, camera, texture, pos, config.key_size, alpha);
+        let Some(key) = keys.first() else {
+            return;
+        };
+        self.ctx.geng.default_font().draw(
+            framebuffer,
+            &self.ui_camera(),
+            &format!("{key:?}"),
+            vec2::splat(geng::TextAlign::CENTER),
+            mat3::translate(pos) * mat3::scale_uniform(config.key_text_size),
+            Rgba::new(1.0, 1.0, 1.0, alpha),
+        );
+    }
+
+    /// Tutorial text next to the icons
+    fn draw_hint(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        key: &str,
+        pos: vec2<f32>,
+        alpha: f32,
+    ) {
+        self.ctx.geng.default_font().draw(
+            framebuffer,
+            &self.ui_camera(),
+            self.ctx.locale.get(key),
+            vec2::splat(geng::TextAlign::CENTER),
+            mat3::translate(pos) * mat3::scale_uniform(self.ctx.config.tutorial.text_size),
+            Rgba::new(1.0, 1.0, 1.0, alpha),
+        );
+    }
+
+    fn draw_share(&self, framebuffer: &mut ugli::Framebuffer, alpha: f32) {
+        let config = &self.ctx.config.share;
+        let color = Rgba::new(1.0, 1.0, 1.0, alpha);
+        let text = |framebuffer: &mut ugli::Framebuffer, text: &str, pos: vec2<f32>| {
+            self.ctx.geng.default_font().draw(
+                framebuffer,
+                &self.ui_camera(),
+                text,
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(pos) * mat3::scale_uniform(config.text_size),
+                color,
+            );
+        };
+        let locale = &self.ctx.locale;
+        if self.ctx.platform.code_prompt().is_some() {
+            text(framebuffer, locale.get("export"), config.export_pos);
+            text(framebuffer, locale.get("import"), config.import_pos);
+        }
+        let mut lines = Vec::new();
+        if let Some(shared) = &self.pending_import {
+            let difficulty = self.ctx.difficulty;
+            lines.push(locale.get("import_confirm").to_owned());
+            lines.push(locale.format(
+                "import_coins",
+                &[("old", &self.save.money), ("new", &shared.money)],
+            ));
+            // Scores are merged, the imported one only counts if it is higher
+            let best_score = self.save.best_score(difficulty);
+            let imported_score = shared.best_scores.get(&difficulty).copied().unwrap_or(0);
+            lines.push(locale.format(
+                "import_best_score",
+                &[
+                    ("old", &best_score),
+                    ("new", &imported_score.max(best_score)),
+                ],
+            ));
+            text(framebuffer, locale.get("yes"), config.yes_pos);
+            text(framebuffer, locale.get("no"), config.no_pos);
+        } else if let Some(message) = &self.share_message {
+            lines.push(message.clone());
+        }
+        for (index, line) in lines.iter().enumerate() {
+            let pos = config.message_pos - vec2(0.0, config.line_height * index as f32);
+            text(framebuffer, line, pos);
+        }
+    }
+
+    fn draw_shop(&self, framebuffer: &mut ugli::Framebuffer, alpha: f32) {
+        let config = &self.ctx.config.shop;
+        for (index, product) in self.shop_products().into_iter().enumerate() {
+            let price = if shop::owned(config, &self.save, &product.id) {
+                self.ctx.locale.get("shop_owned")
             } else {
-                (&self.ctx.assets.tutorial.r, self.ctx.config.tutorial.r_pos)
+                product.price.as_str()
             };
-            self.ctx.render.sprite_ext(
+            self.ctx.geng.default_font().draw(
                 framebuffer,
-                &self.camera,
-                texture,
-                mat4::translate(pos.extend(-self.ctx.config.camera.distance) + self.camera.pos)
-                    * mat4::scale(
-                        texture.size().map(|x| x as f32).extend(1.0)
-                            * self.ctx.config.tutorial.final_scale,
-                    ),
+                &self.ui_camera(),
+                &format!("{}: {price}", product.title),
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(self.shop_line_pos(index)) * mat3::scale_uniform(config.text_size),
                 Rgba::new(1.0, 1.0, 1.0, alpha),
-                false,
             );
         }
+    }
 
-        self.draw_ui(framebuffer);
+    fn draw_stats(&self, framebuffer: &mut ugli::Framebuffer, alpha: f32) {
+        let config = &self.ctx.config.stats;
+        let stats = &self.save.stats;
+        let lines = [
+            ("stats_games_played", stats.games_played),
+            ("stats_total_score", stats.total_score),
+            ("stats_coins_collected", stats.coins_collected),
+        ];
+        for (index, (key, count)) in lines.into_iter().enumerate() {
+            self.ctx.geng.default_font().draw(
+                framebuffer,
+                &self.ui_camera(),
+                &self.ctx.locale.format(key, &[("count", &count)]),
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(config.pos - vec2(0.0, config.line_height * index as f32))
+                    * mat3::scale_uniform(config.text_size),
+                Rgba::new(1.0, 1.0, 1.0, alpha),
+            );
+        }
     }
 
     fn draw_ui(&mut self, framebuffer: &mut ugli::Framebuffer) {
@@ -585,6 +1279,7 @@ impl GameState {
             fov: self.ctx.config.score.fov,
         };
         let camera_bb = camera.view_area(self.framebuffer_size);
+        self.draw_tutorial(framebuffer, &camera);
         self.ctx.render.sprite(
             framebuffer,
             &camera,
@@ -602,7 +1297,7 @@ impl GameState {
         self.ctx.render.sprite(
             framebuffer,
             &camera,
-            if self.score as i32 >= self.best_score {
+            if self.score as i32 >= self.save.best_score(self.ctx.difficulty) {
                 &self.ctx.assets.top1
             } else {
                 &self.ctx.assets.score_icon
@@ -615,9 +1310,66 @@ impl GameState {
             0.0,
             mat4::translate((camera_bb.top_left() + self.ctx.config.score.offset).extend(0.0)),
         );
+        {
+            let alpha = (1.0 - self.started.unwrap_or(0.0)).clamp(0.0, 1.0);
+            let button = &self.ctx.config.difficulty_button;
+            self.ctx.geng.default_font().draw(
+                framebuffer,
+                &self.ui_camera(),
+                self.ctx.locale.get(self.ctx.difficulty.locale_key()),
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(button.pos) * mat3::scale_uniform(button.text_size),
+                Rgba::new(1.0, 1.0, 1.0, alpha),
+            );
+            for (index, pack) in self.obstacle_packs().iter().enumerate() {
+                let mark = if self.save.settings.obstacle_pack_enabled(pack) {
+                    "x"
+                } else {
+                    " "
+                };
+                self.ctx.geng.default_font().draw(
+                    framebuffer,
+                    &self.ui_camera(),
+                    &format!("[{mark}] {pack}"),
+                    vec2::splat(geng::TextAlign::CENTER),
+                    mat3::translate(self.obstacle_pack_line_pos(index))
+                        * mat3::scale_uniform(self.ctx.config.obstacle_packs.text_size),
+                    Rgba::new(1.0, 1.0, 1.0, alpha),
+                );
+            }
+            self.draw_share(framebuffer, alpha);
+            self.draw_shop(framebuffer, alpha);
+        }
         if let Some(finished) = self.finished {
             let alpha = finished.min(1.0);
-            let best_score = self.best_score.to_string();
+            let config = &self.ctx.config.leaderboard;
+            for (index, entry) in self.leaderboard.iter().enumerate() {
+                self.ctx.geng.default_font().draw(
+                    framebuffer,
+                    &self.ui_camera(),
+                    &format!("{}. {} {}", entry.rank, entry.name, entry.score,),
+                    vec2::splat(geng::TextAlign::CENTER),
+                    mat3::translate(config.pos - vec2(0.0, config.line_height * index as f32))
+                        * mat3::scale_uniform(config.text_size),
+                    Rgba::new(1.0, 1.0, 1.0, alpha),
+                );
+            }
+            self.draw_stats(framebuffer, alpha);
+            if self.run_money != 0 {
+                let button = &self.ctx.config.rewarded_ad;
+                self.ctx.geng.default_font().draw(
+                    framebuffer,
+                    &self.ui_camera(),
+                    &self
+                        .ctx
+                        .locale
+                        .format("rewarded_ad", &[("coins", &self.run_money)]),
+                    vec2::splat(geng::TextAlign::CENTER),
+                    mat3::translate(button.pos) * mat3::scale_uniform(button.text_size),
+                    Rgba::new(1.0, 1.0, 1.0, alpha),
+                );
+            }
+            let best_score = self.save.best_score(self.ctx.difficulty).to_string();
             for (i, digit) in best_score.chars().enumerate() {
                 let x =
                     i as f32 * self.ctx.config.digit_size - (best_score.len() as f32 - 2.0) / 2.0;
@@ -639,32 +1391,70 @@ impl GameState {
                 ),
             );
         }
+        if self.menu_open || self.gap_paused {
+            self.draw_menu(framebuffer);
+        }
     }
-    async fn update(&mut self, delta_time: time::Duration) {
-        let delta_time = delta_time.as_secs_f64() as f32;
 
+    /// Pause menu, or just the hint when paused by a frame gap
+    fn draw_menu(&self, framebuffer: &mut ugli::Framebuffer) {
+        let config = &self.ctx.config.pause_menu;
+        self.ctx
+            .render
+            .color_overlay(framebuffer, Rgba::new(0.0, 0.0, 0.0, config.overlay_alpha));
+        let mut lines = vec![("pause_title", config.title_pos)];
+        if self.gap_paused {
+            let hint = if self.ctx.mobile {
+                "pause_hint_touch"
+            } else {
+                "pause_hint_key"
+            };
+            lines.push((hint, config.resume_pos));
+        } else {
+            lines.push(("pause_resume", config.resume_pos));
+            if self.ctx.platform.can_quit() {
+                lines.push(("pause_quit", config.quit_pos));
+            }
+        }
+        for (key, pos) in lines {
+            self.ctx.geng.default_font().draw(
+                framebuffer,
+                &self.ui_camera(),
+                self.ctx.locale.get(key),
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(pos) * mat3::scale_uniform(config.text_size),
+                Rgba::WHITE,
+            );
+        }
+    }
+
+    async fn update(&mut self, delta_time: f32) {
         if self.started.is_some() {
             self.score_fancy.set_value(self.score as i32);
         }
         self.score_fancy.update(delta_time);
 
-        self.money_fancy.set_value(self.money as i32);
+        self.money_fancy.set_value(self.save.money as i32);
         self.money_fancy.update(delta_time);
 
         self.time += delta_time;
         if let Some(time) = &mut self.started {
             *time += delta_time / self.ctx.config.start_time;
         }
+        if self.started.is_some() && self.player.is_some() {
+            self.run_time += delta_time;
+        }
         if self.finished.is_none() && self.player.is_none() {
             self.finished = Some(0.0);
             let score = self.score as i32;
-            if score > self.best_score {
-                self.best_score = score;
-                preferences::save("best_score", &self.best_score);
-            }
+            self.save.update_best_score(self.ctx.difficulty, score);
+            self.save.stats.games_played += 1;
+            self.save.stats.total_score += score.max(0) as u64;
             self.ctx.assets.sfx.death.play();
             self.music = self.ctx.start_music(&self.ctx.assets.music.mallet);
-            self.save_money().await;
+            self.ctx.platform.gameplay_stop();
+            self.need_save = true;
+            self.update_leaderboard();
         }
         if let Some(time) = &mut self.finished {
             *time += delta_time / self.ctx.config.finish_time;
@@ -696,12 +1486,24 @@ impl GameState {
             }
         }
 
+        let mouse_target = self
+            .mouse_control
+            .as_ref()
+            .filter(|mouse| mouse.clicked || self.started.is_some())
+            .map(|mouse| self.raycast(mouse.pos));
+
         if let Some(player) = &mut self.player {
             if let Some(index) = self.coins.iter().position(|&coin| {
                 (coin - player.pos).len() < player.radius + self.ctx.config.coin.radius
             }) {
                 let coin = self.coins.remove(index);
-                self.money += 1;
+                self.save.money += 1;
+                self.save.updated_at = save::now();
+                self.save.stats.coins_collected += 1;
+                // Doubling is offered only if there are ads
+                if self.ctx.ads.available() {
+                    self.run_money += 1;
+                }
                 self.score += self.ctx.config.score.coin;
                 let mut effect = self.ctx.assets.sfx.coin.effect();
                 effect.set_volume(self.ctx.config.sfx.coin_volume);
@@ -736,15 +1538,7 @@ impl GameState {
                 .update(delta_time * player.vel.z.abs() / self.ctx.config.player.fall_speed);
 
             // controls
-            let control_config = if self.touch_control.is_some() {
-                &self.ctx.config.player.touch_control
-            } else {
-                &self.ctx.config.player.keyboard_control
-            };
-            let target_vel = if let Some(touch) = &self.touch_control {
-                (touch.move_delta / self.ctx.config.touch_control.small_radius).clamp_len(..=1.0)
-                    * self.ctx.config.player.touch_control.max_speed
-            } else {
+            let keyboard_dir = {
                 let mut target_vel = vec2::ZERO;
                 let mut control = |keys: &[geng::Key], x: f32, y: f32| {
                     if keys
@@ -760,12 +1554,37 @@ impl GameState {
                     control(&self.ctx.controls.player.down, 0.0, -1.0);
                     control(&self.ctx.controls.player.right, 1.0, 0.0);
                 }
-                target_vel.clamp_len(..=1.0) * self.ctx.config.player.keyboard_control.max_speed
+                target_vel.clamp_len(..=1.0)
+            };
+            let (control_config, target_vel) = if let Some(touch) = &self.touch_control {
+                let config = &self.ctx.config.player.touch_control;
+                (
+                    config,
+                    (touch.move_delta / self.ctx.config.touch_control.small_radius)
+                        .clamp_len(..=1.0)
+                        * config.max_speed,
+                )
+            } else if let Some(target) = mouse_target.filter(|_| keyboard_dir == vec2::ZERO) {
+                let config = &self.ctx.config.player.mouse_control;
+                (
+                    config,
+                    ((target - player.pos.xy()) / self.ctx.config.mouse_control.radius)
+                        .clamp_len(..=1.0)
+                        * config.max_speed,
+                )
+            } else {
+                let config = &self.ctx.config.player.keyboard_control;
+                (config, keyboard_dir * config.max_speed)
             };
-            if self.started.is_none() && (target_vel != vec2::ZERO || self.touch_control.is_some())
+            if self.started.is_none()
+                && self.pending_import.is_none()
+                && (target_vel != vec2::ZERO
+                    || self.touch_control.is_some()
+                    || mouse_target.is_some())
             {
                 self.music = self.ctx.start_music(&self.ctx.assets.music.guitar);
                 self.started = Some(0.0);
+                self.ctx.platform.gameplay_start();
             }
             let target_vel = target_vel * self.started.unwrap_or(0.0).min(1.0);
             assert!(target_vel.x.is_finite());
@@ -988,26 +1807,30 @@ impl GameState {
         while self.obstacles.last().map_or(true, |last| last.z > far) {
             let last_z = self.obstacles.last().map_or(0.0, |last| last.z);
             let z = last_z - thread_rng().gen_range(self.ctx.config.obstacles.distance.range());
-            let obstacle = self
+            let obstacle = self.choose_obstacle(-z);
+            let transform = {
+                let config = obstacle.config.borrow();
+                mat4::rotate_z(Angle::from_degrees(
+                    thread_rng().gen_range(config.rotation.range()),
+                )) * assets::Obstacle::base_transform(
+                    obstacle.sprite.texture.size(),
+                    config.thickness,
+                    self.ctx.config.tube_radius,
+                    obstacle.random_tilt(&mut thread_rng()),
+                )
+            };
+            self.obstacle_history.push_back(obstacle.clone());
+            let max_cooldown = self
                 .ctx
                 .assets
                 .obstacles
-                .choose(&mut thread_rng())
-                .unwrap()
-                .clone();
-            let mut aspect = obstacle.sprite.texture.size().map(|x| x as f32).aspect();
-            let mut transform = mat4::scale(vec3(1.0 / aspect, 1.0, obstacle.config.thickness));
-            if aspect >= 1.0 {
-                // transform *= mat4::rotate_z(Angle::from_degrees(90.0));
-                aspect = 1.0 / aspect;
+                .iter()
+                .map(|obstacle| obstacle.config.borrow().cooldown)
+                .max()
+                .unwrap_or(0);
+            while self.obstacle_history.len() > max_cooldown {
+                self.obstacle_history.pop_front();
             }
-            transform =
-                mat4::scale((vec2(1.0, 1.0 / aspect) * self.ctx.config.tube_radius).extend(1.0))
-                    * transform;
-            transform = mat4::rotate_x(Angle::from_radians(
-                aspect.acos() * if thread_rng().gen() { -1.0 } else { 1.0 },
-            )) * transform;
-            transform = mat4::rotate_z(thread_rng().gen()) * transform;
 
             self.coins.push(
                 vec2(self.ctx.config.tube_radius / 2.0, 0.0)
@@ -1025,20 +1848,57 @@ impl GameState {
             .retain(|obstacle| obstacle.z < self.camera.pos.z + 10.0);
     }
     fn handle_event(&mut self, event: geng::Event) {
+        if let geng::Event::Focused(focused) = event {
+            self.focus_changed(focused);
+            return;
+        }
+        if self.platform_paused {
+            // The SDK is showing something over the game
+            return;
+        }
+        if self.gap_paused {
+            // The input resuming the game does nothing else
+            if let geng::Event::KeyPress { .. }
+            | geng::Event::MousePress { .. }
+            | geng::Event::TouchStart(..) = event
+            {
+                self.resume_after_gap();
+            }
+            return;
+        }
+        if self.menu_open {
+            self.menu_event(event);
+            return;
+        }
         match event {
             geng::Event::KeyPress { key } => {
                 self.key_press(key);
             }
             geng::Event::MousePress { .. } => {
-                if let Some(pos) = self.ctx.geng.window().cursor_position() {
-                    self.touch_start(pos);
+                if self.ctx.mobile {
+                    if let Some(pos) = self.ctx.geng.window().cursor_position() {
+                        self.touch_start(pos);
+                    }
+                } else {
+                    self.mouse_press();
                 }
             }
             geng::Event::CursorMove { position } => {
-                self.touch_move(position);
+                if self.ctx.mobile {
+                    self.touch_move(position);
+                } else {
+                    self.mouse_move(position);
+                }
+            }
+            geng::Event::RawMouseMove { delta } => {
+                if !self.ctx.mobile {
+                    self.mouse_raw_move(delta);
+                }
             }
             geng::Event::MouseRelease { .. } => {
-                self.touch_end();
+                if self.ctx.mobile {
+                    self.touch_end();
+                }
             }
             geng::Event::TouchStart(touch) => {
                 self.touch_start(touch.position);
@@ -1052,22 +1912,35 @@ impl GameState {
             _ => {}
         }
     }
-    async fn save_money(&self) {
-        #[cfg(feature = "yandex")]
-        {
-            if let Err(e) = self
-                .ctx
-                .yandex
-                .player
-                .set_numeric_data("money", self.money as f64)
+    /// Submit the best score and fetch the top entries, finished in [Self::run] without blocking frames
+    fn update_leaderboard(&mut self) {
+        if self.ctx.platform.leaderboards().is_none() {
+            return;
+        }
+        let ctx = self.ctx.clone();
+        let best_score = self.save.best_score(self.ctx.difficulty);
+        let update = async move {
+            let leaderboards = ctx.platform.leaderboards()?;
+            let config = &ctx.config.leaderboard;
+            if let Err(e) = leaderboards
+                .set_score(&config.name, best_score.into())
                 .await
             {
-                log::error!("money could not be saved: {e}");
+                log::error!("best score could not be submitted: {e:#}");
             }
-        }
-        #[cfg(not(feature = "yandex"))]
-        {
-            preferences::save("money", &self.money);
-        }
+            leaderboards
+                .entries(&config.name, config.top, config.around)
+                .await
+                .map_err(|e| log::error!("failed to get leaderboard entries: {e:#}"))
+                .ok()
+        };
+        self.leaderboard_update = Some(update.boxed_local());
+    }
+
+    /// Finished in [Self::run] without blocking frames
+    fn save_progress(&mut self) {
+        let storage = self.ctx.storage.clone();
+        let save = self.save.clone();
+        self.saving = Some(async move { storage.save(&save).await }.boxed_local());
     }
 }
@@ -0,0 +1,52 @@
+use super::*;
+
+/// Watches config files and tells when they were modified.
+///
+/// Polls modification times since that works everywhere we have a filesystem,
+/// where there is no filesystem (web, android assets) nothing ever changes
+pub struct HotReload {
+    timer: Timer,
+    files: Vec<(std::path::PathBuf, Option<std::time::SystemTime>)>,
+}
+
+impl HotReload {
+    const CHECK_INTERVAL: f64 = 0.5;
+
+    pub fn new(ctx: &Ctx) -> Self {
+        Self {
+            timer: Timer::new(),
+            files: ctx
+                .config_files()
+                .into_iter()
+                .map(|path| {
+                    let modified = modified(&path);
+                    (path, modified)
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns true if any of the files changed since last time
+    pub fn check(&mut self) -> bool {
+        if self.timer.elapsed().as_secs_f64() < Self::CHECK_INTERVAL {
+            return false;
+        }
+        self.timer = Timer::new();
+        let mut changed = false;
+        for (path, last_modified) in &mut self.files {
+            let modified = modified(path);
+            if modified != *last_modified {
+                log::info!("{path:?} changed");
+                *last_modified = modified;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &std::path::Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
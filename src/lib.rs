@@ -1,21 +1,38 @@
 use geng::prelude::*;
 
+mod ads;
+mod asset_check;
 mod assets;
 mod config;
 mod controls;
 mod ctx;
+mod difficulty;
 mod easings;
 mod fancy_number;
 mod game_state;
+mod hot_reload;
 mod loading;
+mod locale;
+mod overrides;
 mod particles;
+mod platform;
 mod render;
+mod save;
+mod settings;
+mod share;
+mod shop;
+mod storage;
+mod validation;
 
 use easings::*;
 
+#[cfg(target_os = "android")]
+pub use platform::{set_activity as set_android_activity, Activity as AndroidActivity};
+
 use ctx::Ctx;
 
 async fn run(args: CliArgs, geng: Geng) {
+    let import_save = args.import_save.clone();
     let ctx = match future::select(
         Ctx::load(args, &geng).boxed_local(),
         loading::run(&geng).boxed_local(),
@@ -26,16 +43,54 @@ async fn run(args: CliArgs, geng: Geng) {
         future::Either::Right(_) => return,
     };
     let ctx = &ctx;
-    let game = game_state::GameState::new(ctx).await;
-    #[cfg(feature = "yandex")]
-    ctx.yandex.sdk.ready();
+    let save = ctx.storage.load().await;
+    let save = shop::process_unconsumed(ctx, save).await;
+    let mut game = game_state::GameState::new(ctx, save).await;
+    if let Some(code) = &import_save {
+        game.offer_import(code);
+    }
+    ctx.platform.ready();
     game.run().await;
+    if ctx.platform.can_quit() {
+        ctx.platform.quit();
+    }
 }
 
 #[derive(clap::Parser)]
 struct CliArgs {
     #[clap(long)]
     mobile: Option<bool>,
+    /// Override a config value, e.g. `--set player.fall_speed=60`.
+    /// Keys starting with `render.` or `particles.` go to render.toml and particles.toml
+    #[clap(long = "set", value_name = "KEY=VALUE")]
+    set: Vec<String>,
+    /// Toml file deep merged over the shipped configs, same layout as `--dump-config` output
+    #[clap(long)]
+    config_overlay: Option<std::path::PathBuf>,
+    /// Directory with additional obstacle packs, `obstacle_packs` next to the assets by default
+    #[clap(long)]
+    obstacle_packs: Option<std::path::PathBuf>,
+    /// Language code like `ru`, detected from the platform by default
+    #[clap(long)]
+    language: Option<String>,
+    /// Keep progress in memory only, leaving the saved one untouched
+    #[clap(long)]
+    memory_save: bool,
+    /// Print a code with the saved progress that can be imported elsewhere and exit
+    #[clap(long)]
+    export_save: bool,
+    /// Import progress from a code, asking for confirmation first
+    #[clap(long, value_name = "CODE")]
+    import_save: Option<String>,
+    /// Validate all the configs and exit
+    #[clap(long)]
+    check_config: bool,
+    /// Check obstacle and wall assets and exit
+    #[clap(long)]
+    check_assets: bool,
+    /// Print the effective config with all the overrides applied and exit
+    #[clap(long)]
+    dump_config: bool,
     #[clap(flatten)]
     geng: geng::CliArgs,
 }
@@ -49,6 +104,48 @@ pub fn main() {
     } else {
         cli::parse()
     };
+    if args.export_save {
+        let save = storage::Local::read();
+        println!("{}", share::Shared::new(&save).export());
+        return;
+    }
+    if args.check_config || args.check_assets || args.dump_config {
+        let overrides = futures::executor::block_on(overrides::Overrides::new(
+            args.config_overlay.as_deref(),
+            &args.set,
+        ))
+        .unwrap_or_else(|e| panic!("{e:#}"));
+        if args.dump_config {
+            let dump = futures::executor::block_on(async {
+                let presets = difficulty::load_presets().await?;
+                overrides
+                    .dump(&presets[&storage::Local::read().settings.difficulty])
+                    .await
+            })
+            .unwrap_or_else(|e| panic!("{e:#}"));
+            println!("{dump}");
+            return;
+        }
+        let mut problems = Vec::new();
+        if args.check_config {
+            problems.extend(futures::executor::block_on(validation::check_all(
+                &overrides,
+            )));
+        }
+        if args.check_assets {
+            problems.extend(futures::executor::block_on(asset_check::check_all(
+                &overrides,
+            )));
+        }
+        for problem in &problems {
+            log::error!("{problem}");
+        }
+        if !problems.is_empty() {
+            std::process::exit(1);
+        }
+        log::info!("No problems found");
+        return;
+    }
     let mut options = geng::ContextOptions::default();
     options.window.title = format!(
         "{name} v{version}",
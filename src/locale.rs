@@ -0,0 +1,130 @@
+use super::*;
+
+use std::{collections::HashMap, path::PathBuf};
+use validation::Problem;
+
+/// Used when there are no strings for the detected language
+pub const FALLBACK: &str = "en";
+/// Languages with a string table in `assets/locale`
+pub const LANGUAGES: [&str; 2] = [FALLBACK, "ru"];
+
+type Strings = HashMap<String, String>;
+
+fn path(language: &str) -> PathBuf {
+    run_dir()
+        .join("assets")
+        .join("locale")
+        .join(format!("{language}.toml"))
+}
+
+async fn load_strings(language: &str) -> anyhow::Result<Strings> {
+    file::load_detect(path(language)).await
+}
+
+/// `en-US` and `en_US` become `en`
+fn normalize(language: &str) -> String {
+    language
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// Player-facing text in one language
+pub struct Locale {
+    language: String,
+    strings: Strings,
+    /// Strings missing in the language are taken from here
+    fallback: Strings,
+}
+
+impl Locale {
+    /// Falls back to [FALLBACK] if there is no string table for the language
+    pub async fn load(language: Option<&str>) -> Self {
+        let fallback = load_strings(FALLBACK)
+            .await
+            .unwrap_or_else(|e| panic!("failed to load {FALLBACK} strings: {e:#}"));
+        let language = language.map_or_else(|| FALLBACK.to_owned(), normalize);
+        let strings = if language == FALLBACK {
+            None
+        } else if !LANGUAGES.contains(&language.as_str()) {
+            log::info!("no strings for language {language:?}, using {FALLBACK}");
+            None
+        } else {
+            load_strings(&language)
+                .await
+                .map_err(|e| log::error!("failed to load {language} strings: {e:#}"))
+                .ok()
+        };
+        match strings {
+            Some(strings) => Self {
+                language,
+                strings,
+                fallback,
+            },
+            None => Self {
+                language: FALLBACK.to_owned(),
+                strings: Strings::new(),
+                fallback,
+            },
+        }
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The key itself if no language has it
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings
+            .get(key)
+            .or_else(|| self.fallback.get(key))
+            .map_or(key, String::as_str)
+    }
+
+    /// Replaces `{name}` in the string with the value of the argument with that name
+    pub fn format(&self, key: &str, args: &[(&str, &dyn std::fmt::Display)]) -> String {
+        let mut text = self.get(key).to_owned();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{name}}}"), &value.to_string());
+        }
+        text
+    }
+}
+
+/// Every language must have exactly the keys of the fallback one
+pub async fn check_all() -> Vec<Problem> {
+    let problem = |language: &str, key: Option<&str>, message: String| Problem {
+        file: path(language),
+        key: key.map(str::to_owned),
+        message,
+    };
+    let fallback = match load_strings(FALLBACK).await {
+        Ok(strings) => strings,
+        Err(e) => return vec![problem(FALLBACK, None, format!("{e:#}"))],
+    };
+    let mut problems = Vec::new();
+    for language in LANGUAGES
+        .into_iter()
+        .filter(|&language| language != FALLBACK)
+    {
+        let strings = match load_strings(language).await {
+            Ok(strings) => strings,
+            Err(e) => {
+                problems.push(problem(language, None, format!("{e:#}")));
+                continue;
+            }
+        };
+        for key in fallback.keys().filter(|key| !strings.contains_key(*key)) {
+            problems.push(problem(language, Some(key), "missing".to_owned()));
+        }
+        for key in strings.keys().filter(|key| !fallback.contains_key(*key)) {
+            problems.push(problem(
+                language,
+                Some(key),
+                format!("not in {FALLBACK}, unused"),
+            ));
+        }
+    }
+    problems
+}
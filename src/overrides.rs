@@ -0,0 +1,162 @@
+use super::*;
+
+use anyhow::Context as _;
+use std::path::{Path, PathBuf};
+
+/// Config files that can be overridden.
+///
+/// Overrides share a single namespace: `render` and `particles` tables
+/// go to the corresponding files, everything else goes to config.toml
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFile {
+    Config,
+    Render,
+    Particles,
+}
+
+impl ConfigFile {
+    pub const ALL: [Self; 3] = [Self::Config, Self::Render, Self::Particles];
+
+    pub fn path(self) -> PathBuf {
+        run_dir().join("assets").join(match self {
+            Self::Config => "config.toml",
+            Self::Render => "render.toml",
+            Self::Particles => "particles.toml",
+        })
+    }
+
+    fn section(self) -> Option<&'static str> {
+        match self {
+            Self::Config => None,
+            Self::Render => Some("render"),
+            Self::Particles => Some("particles"),
+        }
+    }
+}
+
+/// Values from the overlay file and `--set` arguments layered over shipped configs
+#[derive(Debug, Default)]
+pub struct Overrides {
+    overlay: toml::Table,
+}
+
+impl Overrides {
+    pub async fn new(overlay: Option<&Path>, set: &[String]) -> anyhow::Result<Self> {
+        let mut result = toml::Table::new();
+        if let Some(path) = overlay {
+            let overlay: toml::Table = file::load_detect(path)
+                .await
+                .with_context(|| format!("failed to load config overlay {path:?}"))?;
+            merge(&mut result, &overlay);
+        }
+        for arg in set {
+            let (key, value) = arg
+                .split_once('=')
+                .with_context(|| format!("expected key=value, got {arg:?}"))?;
+            set_value(&mut result, key.trim(), parse_value(value.trim()));
+        }
+        Ok(Self { overlay: result })
+    }
+
+    /// Part of the overlay that applies to the given file
+    pub fn layer(&self, file: ConfigFile) -> toml::Table {
+        match file.section() {
+            Some(section) => match self.overlay.get(section) {
+                Some(toml::Value::Table(table)) => table.clone(),
+                _ => toml::Table::new(),
+            },
+            None => self
+                .overlay
+                .iter()
+                .filter(|(key, _)| {
+                    !ConfigFile::ALL
+                        .iter()
+                        .any(|file| file.section() == Some(key.as_str()))
+                })
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        }
+    }
+
+    pub async fn load<T: DeserializeOwned + validation::Validate>(
+        &self,
+        file: ConfigFile,
+    ) -> Result<T, validation::Problems> {
+        validation::load_layered(file.path(), &[self.layer(file)]).await
+    }
+
+    /// config.toml with a difficulty preset applied before the overrides
+    pub async fn load_config(
+        &self,
+        preset: &toml::Table,
+    ) -> Result<config::Config, validation::Problems> {
+        validation::load_layered(
+            ConfigFile::Config.path(),
+            &[preset.clone(), self.layer(ConfigFile::Config)],
+        )
+        .await
+    }
+
+    /// All the configs merged with the difficulty preset and overrides in a single file,
+    /// that can later be used as an overlay to get exactly the same values
+    pub async fn dump(&self, preset: &toml::Table) -> anyhow::Result<String> {
+        let mut result = toml::Table::new();
+        for file in ConfigFile::ALL {
+            let path = file.path();
+            let mut table: toml::Table = file::load_detect(&path)
+                .await
+                .with_context(|| format!("failed to load {path:?}"))?;
+            if file == ConfigFile::Config {
+                merge(&mut table, preset);
+            }
+            merge(&mut table, &self.layer(file));
+            match file.section() {
+                Some(section) => {
+                    result.insert(section.to_owned(), toml::Value::Table(table));
+                }
+                None => merge(&mut result, &table),
+            }
+        }
+        Ok(toml::to_string(&result)?)
+    }
+}
+
+/// Deep merge, tables are merged recursively, everything else is replaced
+pub fn merge(base: &mut toml::Table, overlay: &toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn set_value(table: &mut toml::Table, key: &str, value: toml::Value) {
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            let entry = table
+                .entry(head)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if !entry.is_table() {
+                *entry = toml::Value::Table(toml::Table::new());
+            }
+            let toml::Value::Table(inner) = entry else {
+                unreachable!()
+            };
+            set_value(inner, rest, value);
+        }
+        None => {
+            table.insert(key.to_owned(), value);
+        }
+    }
+}
+
+/// Values are parsed as toml, falling back to plain strings so that `fog_color=black` works
+fn parse_value(value: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_owned()))
+}
@@ -1,5 +1,7 @@
 use super::*;
 
+use overrides::{ConfigFile, Overrides};
+
 #[derive(ugli::Vertex)]
 struct Vertex {
     a_pos: vec2<f32>,
@@ -27,7 +29,7 @@ struct Assets {
     shaders: Shaders,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct Config {
     pub movement: Rc<SpawnerConfig>,
     pub bounce: Rc<SpawnerConfig>,
@@ -49,6 +51,34 @@ pub struct SpawnerConfig {
     extra_lightness: f32,
 }
 
+impl validation::Validate for SpawnerConfig {
+    fn validate(&self, v: &mut validation::Validator) {
+        v.positive("freq", self.freq);
+        v.non_negative("life", self.life);
+        v.non_negative("size", self.size);
+        // used as random ranges, which can not be empty
+        for (key, value) in [
+            ("extra_vel", self.extra_vel),
+            ("extra_life", self.extra_life),
+            ("extra_size", self.extra_size),
+            ("extra_hue", self.extra_hue),
+            ("extra_saturation", self.extra_saturation),
+            ("extra_lightness", self.extra_lightness),
+        ] {
+            v.positive(key, value);
+        }
+    }
+}
+
+impl validation::Validate for Config {
+    fn validate(&self, v: &mut validation::Validator) {
+        v.nested("movement", &*self.movement);
+        v.nested("bounce", &*self.bounce);
+        v.nested("death", &*self.death);
+        v.nested("coin", &*self.coin);
+    }
+}
+
 pub struct ParticleSpawner {
     pub pos: vec3<f32>,
     pub vel: vec3<f32>,
@@ -101,25 +131,27 @@ pub struct Inner {
     timer: Timer,
     quad: ugli::VertexBuffer<Vertex>,
     assets: Assets,
-    pub config: Config,
     instances: RefCell<ugli::VertexBuffer<Instance>>,
 }
 
-#[derive(Deref)]
+#[derive(Deref, Clone)]
 pub struct Particles {
+    #[deref]
     inner: Rc<Inner>,
+    pub config: Config,
 }
 
 impl Particles {
-    pub async fn init(geng: &Geng) -> Self {
+    pub async fn init(geng: &Geng, overrides: &Overrides) -> Self {
         let assets = geng
             .asset_manager()
             .load(run_dir().join("assets"))
             .await
             .unwrap();
-        let config = file::load_detect(run_dir().join("assets").join("particles.toml"))
+        let config = overrides
+            .load(ConfigFile::Particles)
             .await
-            .unwrap();
+            .unwrap_or_else(|e| panic!("{e}"));
         let quad = ugli::VertexBuffer::new_static(
             geng.ugli(),
             [(0, 0), (0, 1), (1, 1), (1, 0)]
@@ -135,11 +167,20 @@ impl Particles {
                 timer: Timer::new(),
                 quad,
                 assets,
-                config,
                 instances: RefCell::new(ugli::VertexBuffer::new_dynamic(geng.ugli(), vec![])),
             }),
+            config,
         }
     }
+
+    /// Reread particles.toml, already spawned particles are kept
+    pub async fn reload(&self, overrides: &Overrides) -> anyhow::Result<Self> {
+        let config = overrides.load(ConfigFile::Particles).await?;
+        Ok(Self {
+            inner: self.inner.clone(),
+            config,
+        })
+    }
     pub fn spawner(&self, config: &Rc<SpawnerConfig>) -> ParticleSpawner {
         ParticleSpawner {
             pos: vec3::ZERO,
@@ -0,0 +1,169 @@
+use super::*;
+
+#[cfg(target_os = "android")]
+mod android;
+#[cfg(target_os = "android")]
+pub use android::{set_activity, Activity};
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+mod native;
+#[cfg(target_arch = "wasm32")]
+mod web;
+#[cfg(feature = "yandex")]
+mod yandex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Device {
+    Desktop,
+    /// Phones and tablets, played with touch controls
+    Mobile,
+}
+
+/// Sent by the platform on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Stop the gameplay and sounds, like when the game is hidden or an ad is shown
+    Pause,
+    /// The pause is over
+    Resume,
+}
+
+pub struct LeaderboardEntry {
+    pub rank: u32,
+    pub name: String,
+    /// Formatted by the platform
+    pub score: String,
+}
+
+pub trait Leaderboards {
+    fn set_score<'a>(
+        &'a self,
+        name: &'a str,
+        score: i64,
+    ) -> future::LocalBoxFuture<'a, anyhow::Result<()>>;
+    /// The top entries and the ones around the player, sorted by rank
+    fn entries<'a>(
+        &'a self,
+        name: &'a str,
+        top: u32,
+        around: u32,
+    ) -> future::LocalBoxFuture<'a, anyhow::Result<Vec<LeaderboardEntry>>>;
+}
+
+/// Item sold for real money
+pub struct Product {
+    pub id: String,
+    pub title: String,
+    /// Formatted with the currency
+    pub price: String,
+}
+
+/// Paid product, has to be consumed once credited
+pub struct Purchase {
+    pub product_id: String,
+    pub token: String,
+}
+
+#[derive(Debug)]
+pub enum PurchaseError {
+    /// Closed by the player, not worth telling about
+    Cancelled,
+    Failed(anyhow::Error),
+}
+
+impl std::fmt::Display for PurchaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "purchase cancelled"),
+            Self::Failed(e) => write!(f, "{e:#}"),
+        }
+    }
+}
+
+impl std::error::Error for PurchaseError {}
+
+pub trait Payments {
+    /// Loaded once on startup
+    fn catalog(&self) -> &[Product];
+    fn purchase<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> future::LocalBoxFuture<'a, Result<Purchase, PurchaseError>>;
+    /// Paid for but never consumed, like when the game was closed while paying
+    fn unconsumed(&self) -> future::LocalBoxFuture<'_, anyhow::Result<Vec<Purchase>>>;
+    fn consume<'a>(
+        &'a self,
+        purchase: &'a Purchase,
+    ) -> future::LocalBoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// Showing and entering progress codes in game
+pub trait CodePrompt {
+    /// Show the code in a way it can be copied from
+    fn show_code(&self, message: &str, code: &str);
+    /// None if cancelled
+    fn ask_code(&self, message: &str) -> Option<String>;
+}
+
+/// Everything that depends on where the game runs, chosen once in [init]
+pub trait Platform {
+    fn name(&self) -> &str;
+    fn device(&self) -> Device;
+    /// Language code like `en`, None if unknown
+    fn language(&self) -> Option<String>;
+    /// Whether the game can close itself, browsers can not close the tab
+    fn can_quit(&self) -> bool;
+    /// Close the game once it is done, only called if [Self::can_quit]
+    fn quit(&self) {}
+    /// None if codes can only be exported from the command line
+    fn code_prompt(&self) -> Option<&dyn CodePrompt> {
+        None
+    }
+    /// Where progress is kept besides the device itself
+    fn cloud_storage(&self) -> Option<Box<dyn storage::Backend>> {
+        None
+    }
+    fn ads(&self) -> Option<Rc<dyn ads::AdBackend>> {
+        None
+    }
+    fn leaderboards(&self) -> Option<&dyn Leaderboards> {
+        None
+    }
+    fn payments(&self) -> Option<&dyn Payments> {
+        None
+    }
+    /// Events received since the last call, oldest first, None when there are no more.
+    ///
+    /// Drained once per frame
+    fn next_event(&self) -> Option<Event> {
+        None
+    }
+    /// The game has loaded and is ready to play
+    fn ready(&self) {}
+    /// Player started playing, like after the start screen
+    fn gameplay_start(&self) {}
+    /// Player stopped playing, like on death
+    fn gameplay_stop(&self) {}
+}
+
+/// Yandex Games if the SDK loads, otherwise whatever the game was built for
+pub async fn init(config: &config::Config) -> Rc<dyn Platform> {
+    let base: Rc<dyn Platform> = {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                Rc::new(web::Web)
+            } else if #[cfg(target_os = "android")] {
+                Rc::new(android::Android::new())
+            } else {
+                Rc::new(native::Native)
+            }
+        }
+    };
+    #[cfg(feature = "yandex")]
+    match yandex::Yandex::init(&config.sdk, base.clone()).await {
+        Ok(yandex) => return Rc::new(yandex),
+        Err(e) => log::error!("running without the SDK: {e}"),
+    }
+    #[cfg(not(feature = "yandex"))]
+    let _ = config;
+    base
+}
@@ -0,0 +1,69 @@
+use super::*;
+
+use std::sync::OnceLock;
+
+/// Activity calls made through JNI by the android crate, which has the bindings
+pub struct Activity {
+    /// False while the app is in the background or covered
+    pub focused: fn() -> bool,
+    pub finish: fn(),
+    /// Device language as a BCP 47 tag, like `ru-RU`
+    pub language: fn() -> Option<String>,
+}
+
+static ACTIVITY: OnceLock<Activity> = OnceLock::new();
+
+/// Called by the android crate before [crate::main]
+pub fn set_activity(activity: Activity) {
+    if ACTIVITY.set(activity).is_err() {
+        log::warn!("android activity set twice");
+    }
+}
+
+pub struct Android {
+    /// Last seen focus of the activity, changes are sent as events
+    focused: Cell<bool>,
+}
+
+impl Android {
+    pub fn new() -> Self {
+        Self {
+            focused: Cell::new(true),
+        }
+    }
+}
+
+impl Platform for Android {
+    fn name(&self) -> &str {
+        "android"
+    }
+
+    fn device(&self) -> Device {
+        Device::Mobile
+    }
+
+    fn language(&self) -> Option<String> {
+        (ACTIVITY.get()?.language)()
+    }
+
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    fn quit(&self) {
+        if let Some(activity) = ACTIVITY.get() {
+            (activity.finish)();
+        }
+    }
+
+    /// Polled from the activity, so it does not depend on the window reporting focus changes.
+    ///
+    /// Goes through JNI, so the game only asks once per frame
+    fn next_event(&self) -> Option<Event> {
+        let focused = (ACTIVITY.get()?.focused)();
+        if self.focused.replace(focused) == focused {
+            return None;
+        }
+        Some(if focused { Event::Resume } else { Event::Pause })
+    }
+}
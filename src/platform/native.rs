@@ -0,0 +1,37 @@
+use super::*;
+
+/// Desktop builds, and iOS which has nothing special yet
+pub struct Native;
+
+impl Platform for Native {
+    fn name(&self) -> &str {
+        "native"
+    }
+
+    fn device(&self) -> Device {
+        if cfg!(target_os = "ios") {
+            Device::Mobile
+        } else {
+            Device::Desktop
+        }
+    }
+
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    /// From the locale environment variables, like `en_US.UTF-8`
+    fn language(&self) -> Option<String> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty() && locale != "C" && locale != "POSIX")
+            .map(|locale| {
+                locale
+                    .split(['_', '.', '@'])
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned()
+            })
+    }
+}
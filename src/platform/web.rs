@@ -0,0 +1,61 @@
+use super::*;
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(module = "/src/detectmobilebrowser.js")]
+extern "C" {
+    fn is_mobile_or_tablet() -> bool;
+}
+
+#[wasm_bindgen(
+    inline_js = "export function navigator_language() { return navigator.language ?? null; }"
+)]
+extern "C" {
+    /// Language of the browser UI
+    fn navigator_language() -> Option<String>;
+}
+
+#[wasm_bindgen]
+extern "C" {
+    fn prompt(message: &str, default: &str) -> Option<String>;
+}
+
+/// Plain browser, nothing but local storage
+pub struct Web;
+
+impl Platform for Web {
+    fn name(&self) -> &str {
+        "web"
+    }
+
+    fn device(&self) -> Device {
+        if is_mobile_or_tablet() {
+            Device::Mobile
+        } else {
+            Device::Desktop
+        }
+    }
+
+    fn language(&self) -> Option<String> {
+        navigator_language()
+    }
+
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    fn code_prompt(&self) -> Option<&dyn CodePrompt> {
+        Some(self)
+    }
+}
+
+/// Browser prompt dialogs, the code can be copied from the text field
+impl CodePrompt for Web {
+    fn show_code(&self, message: &str, code: &str) {
+        prompt(message, code);
+    }
+
+    fn ask_code(&self, message: &str) -> Option<String> {
+        prompt(message, "")
+    }
+}
@@ -0,0 +1,541 @@
+use super::*;
+
+use save::SaveData;
+
+/// Sdk errors hold a `JsValue` so they can not be sent across threads like anyhow wants
+fn sdk_error(e: ysdk::Error) -> anyhow::Error {
+    anyhow::anyhow!("{e}")
+}
+
+/// Shared with the ads and the cloud storage
+struct Sdk {
+    sdk: ysdk::Ysdk,
+    player: ysdk::Player,
+}
+
+impl ads::AdBackend for Sdk {
+    fn show_fullscreen(&self) -> future::LocalBoxFuture<'_, anyhow::Result<bool>> {
+        async move { self.sdk.show_fullscreen_adv().await.map_err(sdk_error) }.boxed_local()
+    }
+
+    fn show_rewarded(&self) -> future::LocalBoxFuture<'_, anyhow::Result<bool>> {
+        async move { self.sdk.show_rewarded_video().await.map_err(sdk_error) }.boxed_local()
+    }
+}
+
+/// Whole save in the player data, plus money and best scores as stats
+struct Cloud {
+    sdk: Rc<Sdk>,
+    /// For every request of a save
+    timeout: std::time::Duration,
+}
+
+/// Layout of the player data
+#[derive(Serialize, Deserialize)]
+struct PlayerData {
+    save: SaveData,
+}
+
+impl storage::Backend for Cloud {
+    fn name(&self) -> &str {
+        "yandex"
+    }
+
+    fn load(&self) -> future::LocalBoxFuture<'_, anyhow::Result<Option<SaveData>>> {
+        async move {
+            let player = &self.sdk.player;
+            if let Some(data) = player.data::<PlayerData>().await.map_err(sdk_error)? {
+                let mut save = data.save;
+                save.migrate();
+                return Ok(Some(save));
+            }
+            // Before the save was kept in the player data only the money was in the cloud
+            let Some(money) = player.numeric_data("money").await.map_err(sdk_error)? else {
+                return Ok(None);
+            };
+            let mut save = SaveData {
+                version: save::VERSION,
+                money: money as u64,
+                ..default()
+            };
+            for difficulty in difficulty::Difficulty::ALL {
+                let score = player
+                    .numeric_data(difficulty.best_score_key())
+                    .await
+                    .map_err(sdk_error)?;
+                if let Some(score) = score {
+                    save.update_best_score(difficulty, score as i32);
+                }
+            }
+            Ok(Some(save))
+        }
+        .boxed_local()
+    }
+
+    fn save<'a>(&'a self, save: &'a SaveData) -> future::LocalBoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let player = &self.sdk.player;
+            let data = PlayerData { save: save.clone() };
+            ysdk::timeout(self.timeout, player.set_data(&data, false))
+                .await
+                .map_err(sdk_error)?;
+            let mut stats = vec![("money", save.money as f64)];
+            stats.extend(difficulty::Difficulty::ALL.map(|difficulty| {
+                (
+                    difficulty.best_score_key(),
+                    save.best_score(difficulty) as f64,
+                )
+            }));
+            ysdk::timeout(self.timeout, player.set_stats(&stats))
+                .await
+                .map_err(sdk_error)
+        }
+        .boxed_local()
+    }
+}
+
+impl Leaderboards for ysdk::Leaderboards {
+    fn set_score<'a>(
+        &'a self,
+        name: &'a str,
+        score: i64,
+    ) -> future::LocalBoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            ysdk::Leaderboards::set_score(self, name, score, None)
+                .await
+                .map_err(sdk_error)
+        }
+        .boxed_local()
+    }
+
+    fn entries<'a>(
+        &'a self,
+        name: &'a str,
+        top: u32,
+        around: u32,
+    ) -> future::LocalBoxFuture<'a, anyhow::Result<Vec<LeaderboardEntry>>> {
+        async move {
+            let options = ysdk::LeaderboardEntriesOptions {
+                include_user: around != 0,
+                quantity_around: around.max(1),
+                quantity_top: top,
+            };
+            let entries = ysdk::Leaderboards::entries(self, name, &options)
+                .await
+                .map_err(sdk_error)?;
+            Ok(entries
+                .entries
+                .into_iter()
+                .map(|entry| LeaderboardEntry {
+                    rank: entry.rank,
+                    name: entry.player.public_name,
+                    score: entry.formatted_score,
+                })
+                .collect())
+        }
+        .boxed_local()
+    }
+}
+
+struct YandexPayments {
+    payments: ysdk::Payments,
+    catalog: Vec<Product>,
+}
+
+impl Payments for YandexPayments {
+    fn catalog(&self) -> &[Product] {
+        &self.catalog
+    }
+
+    fn purchase<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> future::LocalBoxFuture<'a, Result<Purchase, PurchaseError>> {
+        async move {
+            match self.payments.purchase(id, None).await {
+                Ok(purchase) => Ok(Purchase {
+                    product_id: purchase.product_id,
+                    token: purchase.purchase_token,
+                }),
+                Err(ysdk::Error::PurchaseCancelled) => Err(PurchaseError::Cancelled),
+                Err(e) => Err(PurchaseError::Failed(sdk_error(e))),
+            }
+        }
+        .boxed_local()
+    }
+
+    fn unconsumed(&self) -> future::LocalBoxFuture<'_, anyhow::Result<Vec<Purchase>>> {
+        async move {
+            let purchases = self.payments.purchases().await.map_err(sdk_error)?;
+            Ok(purchases
+                .into_iter()
+                .map(|purchase| Purchase {
+                    product_id: purchase.product_id,
+                    token: purchase.purchase_token,
+                })
+                .collect())
+        }
+        .boxed_local()
+    }
+
+    fn consume<'a>(
+        &'a self,
+        purchase: &'a Purchase,
+    ) -> future::LocalBoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let purchase = ysdk::Purchase {
+                product_id: purchase.product_id.clone(),
+                purchase_token: purchase.token.clone(),
+                developer_payload: None,
+            };
+            self.payments.consume(&purchase).await.map_err(sdk_error)
+        }
+        .boxed_local()
+    }
+}
+
+pub struct Yandex {
+    /// Platform the game runs on inside the Yandex Games page
+    base: Rc<dyn Platform>,
+    sdk: Rc<Sdk>,
+    save_timeout: std::time::Duration,
+    /// None if leaderboards are unavailable
+    leaderboards: Option<ysdk::Leaderboards>,
+    /// None if purchases are unavailable
+    payments: Option<YandexPayments>,
+}
+
+impl Yandex {
+    /// Only the SDK itself and the player are required, the rest is optional.
+    ///
+    /// Every call gets what is left of the timeout, a hanging one must not keep the game from starting
+    pub async fn init(config: &config::Sdk, base: Rc<dyn Platform>) -> Result<Self, ysdk::Error> {
+        let timer = Timer::new();
+        let left = || {
+            let left = config.init_timeout as f64 - timer.elapsed().as_secs_f64();
+            std::time::Duration::from_secs_f64(left.max(0.0))
+        };
+        let sdk = ysdk::Ysdk::init(left()).await?;
+        let player = ysdk::timeout(left(), sdk.player(false)).await?;
+        let leaderboards = ysdk::timeout(left(), sdk.leaderboards())
+            .await
+            .map_err(|e| log::error!("leaderboards are unavailable: {e}"))
+            .ok();
+        let payments = match ysdk::timeout(left(), sdk.payments()).await {
+            Ok(payments) => {
+                let catalog = ysdk::timeout(left(), payments.catalog())
+                    .await
+                    .unwrap_or_else(|e| {
+                        log::error!("failed to load the catalog: {e}");
+                        Vec::new()
+                    });
+                Some(YandexPayments {
+                    payments,
+                    catalog: catalog
+                        .into_iter()
+                        .map(|product| Product {
+                            id: product.id,
+                            title: product.title,
+                            price: product.price,
+                        })
+                        .collect(),
+                })
+            }
+            Err(e) => {
+                log::error!("payments are unavailable: {e}");
+                None
+            }
+        };
+        Ok(Self {
+            base,
+            sdk: Rc::new(Sdk { sdk, player }),
+            save_timeout: std::time::Duration::from_secs_f32(config.save_timeout),
+            leaderboards,
+            payments,
+        })
+    }
+}
+
+impl Platform for Yandex {
+    fn name(&self) -> &str {
+        "yandex"
+    }
+
+    fn device(&self) -> Device {
+        match self.sdk.sdk.device_info().device_type() {
+            ysdk::DeviceType::Desktop => Device::Desktop,
+            ysdk::DeviceType::Mobile | ysdk::DeviceType::Tablet => Device::Mobile,
+        }
+    }
+
+    fn language(&self) -> Option<String> {
+        Some(self.sdk.sdk.language())
+    }
+
+    fn can_quit(&self) -> bool {
+        self.base.can_quit()
+    }
+
+    fn quit(&self) {
+        self.base.quit();
+    }
+
+    fn code_prompt(&self) -> Option<&dyn CodePrompt> {
+        self.base.code_prompt()
+    }
+
+    fn cloud_storage(&self) -> Option<Box<dyn storage::Backend>> {
+        Some(Box::new(Cloud {
+            sdk: self.sdk.clone(),
+            timeout: self.save_timeout,
+        }))
+    }
+
+    fn ads(&self) -> Option<Rc<dyn ads::AdBackend>> {
+        Some(self.sdk.clone())
+    }
+
+    fn leaderboards(&self) -> Option<&dyn Leaderboards> {
+        self.leaderboards
+            .as_ref()
+            .map(|leaderboards| leaderboards as &dyn Leaderboards)
+    }
+
+    fn payments(&self) -> Option<&dyn Payments> {
+        self.payments
+            .as_ref()
+            .map(|payments| payments as &dyn Payments)
+    }
+
+    fn next_event(&self) -> Option<Event> {
+        self.sdk.sdk.next_event().map(|event| match event {
+            ysdk::Event::Pause => Event::Pause,
+            ysdk::Event::Resume => Event::Resume,
+        })
+    }
+
+    fn ready(&self) {
+        self.sdk.sdk.ready();
+    }
+
+    fn gameplay_start(&self) {
+        self.sdk.sdk.gameplay_start();
+    }
+
+    fn gameplay_stop(&self) {
+        self.sdk.sdk.gameplay_stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use config::ShopReward;
+    use difficulty::Difficulty;
+    use futures::executor::block_on;
+    use std::collections::HashMap;
+    use storage::Backend as _;
+    use ysdk::{Mock, PurchaseOutcome, RewardedOutcome};
+
+    fn sdk(mock: &Rc<Mock>) -> Rc<Sdk> {
+        let sdk = ysdk::Ysdk::mock(mock.clone());
+        let player = block_on(sdk.player(false)).unwrap();
+        Rc::new(Sdk { sdk, player })
+    }
+
+    fn cloud(mock: &Rc<Mock>) -> Cloud {
+        Cloud {
+            sdk: sdk(mock),
+            timeout: std::time::Duration::from_secs(1),
+        }
+    }
+
+    fn product(id: &str) -> ysdk::Product {
+        ysdk::Product {
+            id: id.to_owned(),
+            title: id.to_owned(),
+            description: String::new(),
+            image_uri: String::new(),
+            price: "10 YAN".to_owned(),
+            price_value: "10".to_owned(),
+            price_currency_code: "YAN".to_owned(),
+        }
+    }
+
+    fn shop() -> config::Shop {
+        config::Shop {
+            pos: vec2::ZERO,
+            line_height: 1.0,
+            width: 1.0,
+            text_size: 1.0,
+            products: HashMap::from([
+                ("coins".to_owned(), ShopReward::Coins(1000)),
+                ("skin".to_owned(), ShopReward::Unlock("skin_cat".to_owned())),
+            ]),
+        }
+    }
+
+    #[test]
+    fn cloud_round_trip() {
+        let mock = Rc::new(Mock::default());
+        let cloud = cloud(&mock);
+        assert!(block_on(cloud.load()).unwrap().is_none());
+
+        let mut save = SaveData {
+            version: save::VERSION,
+            money: 250,
+            ..default()
+        };
+        save.update_best_score(Difficulty::Hard, 40);
+        save.unlocks.insert("skin_cat".to_owned());
+        block_on(cloud.save(&save)).unwrap();
+        // Stats are kept for builds that only read the money
+        let stats = mock.stats.borrow().clone();
+        assert_eq!(stats["money"], 250.0);
+        assert_eq!(stats[Difficulty::Hard.best_score_key()], 40.0);
+        assert_eq!(stats[Difficulty::Normal.best_score_key()], 0.0);
+
+        let loaded = block_on(cloud.load()).unwrap().unwrap();
+        assert_eq!(loaded.money, 250);
+        assert_eq!(loaded.best_score(Difficulty::Hard), 40);
+        assert_eq!(loaded.unlocks, save.unlocks);
+    }
+
+    #[test]
+    fn cloud_imports_legacy_stats() {
+        let mock = Rc::new(Mock::default());
+        mock.stats.borrow_mut().extend([
+            ("money".to_owned(), 300.0),
+            (Difficulty::Normal.best_score_key().to_owned(), 75.0),
+        ]);
+        let save = block_on(cloud(&mock).load()).unwrap().unwrap();
+        assert_eq!(save.version, save::VERSION);
+        assert_eq!(save.money, 300);
+        assert_eq!(save.best_score(Difficulty::Normal), 75);
+        assert_eq!(save.best_score(Difficulty::Hard), 0);
+    }
+
+    #[test]
+    fn cloud_fails_offline() {
+        let mock = Rc::new(Mock::default());
+        mock.offline.set(true);
+        let cloud = cloud(&mock);
+        assert!(block_on(cloud.load()).is_err());
+        assert!(block_on(cloud.save(&SaveData::default())).is_err());
+    }
+
+    #[test]
+    fn rewarded_ads() {
+        let mock = Rc::new(Mock::default());
+        mock.rewarded_videos
+            .borrow_mut()
+            .extend([RewardedOutcome::Rewarded, RewardedOutcome::Closed]);
+        let backend: Rc<dyn ads::AdBackend> = sdk(&mock);
+        let ads = ads::Ads::new(Some(backend));
+        assert!(ads.available());
+        assert!(block_on(ads.show_rewarded()).unwrap());
+        assert!(!block_on(ads.show_rewarded()).unwrap());
+        mock.offline.set(true);
+        assert!(block_on(ads.show_rewarded()).is_err());
+    }
+
+    #[test]
+    fn unconsumed_purchases_are_credited_once() {
+        let mock = Rc::new(Mock::default());
+        mock.catalog
+            .borrow_mut()
+            .extend([product("coins"), product("skin")]);
+        let sdk = ysdk::Ysdk::mock(mock.clone());
+        let payments = block_on(sdk.payments()).unwrap();
+        // Paid for, but the game closed before crediting
+        block_on(payments.purchase("coins", None)).unwrap();
+        block_on(payments.purchase("skin", None)).unwrap();
+        mock.purchase_outcomes
+            .borrow_mut()
+            .push_back(PurchaseOutcome::Cancelled);
+        assert!(block_on(payments.purchase("coins", None)).is_err());
+        let payments = YandexPayments {
+            payments,
+            catalog: Vec::new(),
+        };
+        let storage = storage::Storage::new(vec![Box::<storage::Memory>::default()]);
+
+        let save = block_on(shop::credit_unconsumed(
+            &shop(),
+            &storage,
+            &payments,
+            SaveData::default(),
+        ));
+        assert_eq!(save.money, 1000);
+        assert!(save.unlocks.contains("skin_cat"));
+        assert!(mock.purchases.borrow().is_empty());
+        assert!(save.credited_purchases.is_empty());
+        assert_eq!(block_on(storage.load()).money, 1000);
+
+        // Nothing is left to credit on the next start
+        let save = block_on(shop::credit_unconsumed(&shop(), &storage, &payments, save));
+        assert_eq!(save.money, 1000);
+    }
+
+    #[test]
+    fn failed_consume_is_not_credited_twice() {
+        let mock = Rc::new(Mock::default());
+        mock.catalog.borrow_mut().push(product("coins"));
+        let sdk = ysdk::Ysdk::mock(mock.clone());
+        let payments = YandexPayments {
+            payments: block_on(sdk.payments()).unwrap(),
+            catalog: Vec::new(),
+        };
+        block_on(payments.payments.purchase("coins", None)).unwrap();
+        let storage = storage::Storage::new(vec![Box::<storage::Memory>::default()]);
+
+        // Credited and saved, but the connection drops before consuming
+        let credited = block_on(shop::credit_unconsumed(
+            &shop(),
+            &storage,
+            &FailingConsume(&payments),
+            SaveData::default(),
+        ));
+        assert_eq!(credited.money, 1000);
+        assert_eq!(credited.credited_purchases.len(), 1);
+        assert_eq!(mock.purchases.borrow().len(), 1);
+
+        let save = block_on(shop::credit_unconsumed(
+            &shop(),
+            &storage,
+            &payments,
+            credited,
+        ));
+        assert_eq!(save.money, 1000);
+        assert!(mock.purchases.borrow().is_empty());
+        assert!(save.credited_purchases.is_empty());
+    }
+
+    /// Payments that lose the connection right before consuming
+    struct FailingConsume<'a>(&'a YandexPayments);
+
+    impl Payments for FailingConsume<'_> {
+        fn catalog(&self) -> &[Product] {
+            self.0.catalog()
+        }
+
+        fn purchase<'a>(
+            &'a self,
+            id: &'a str,
+        ) -> future::LocalBoxFuture<'a, Result<Purchase, PurchaseError>> {
+            self.0.purchase(id)
+        }
+
+        fn unconsumed(&self) -> future::LocalBoxFuture<'_, anyhow::Result<Vec<Purchase>>> {
+            self.0.unconsumed()
+        }
+
+        fn consume<'a>(
+            &'a self,
+            _purchase: &'a Purchase,
+        ) -> future::LocalBoxFuture<'a, anyhow::Result<()>> {
+            future::ready(Err(anyhow::anyhow!("offline"))).boxed_local()
+        }
+    }
+}
@@ -2,6 +2,8 @@ use geng_sprite_shape::ThickSprite;
 
 use super::*;
 
+use overrides::{ConfigFile, Overrides};
+
 #[derive(ugli::Vertex)]
 pub struct Vertex {
     pub a_pos: vec3<f32>,
@@ -9,13 +11,16 @@ pub struct Vertex {
     pub a_normal: vec3<f32>,
 }
 
+#[derive(Clone)]
 pub struct Render {
-    cylinder: ugli::VertexBuffer<Vertex>,
-    quad: ugli::VertexBuffer<Vertex>,
-    assets: Assets,
+    cylinder: Rc<ugli::VertexBuffer<Vertex>>,
+    quad: Rc<ugli::VertexBuffer<Vertex>>,
+    assets: Rc<Assets>,
     pub player: Cell<Option<(vec3<f32>, f32)>>,
+    /// Shadow distances are relative to it, from the game config
+    pub tube_radius: f32,
     pub config: Config,
-    pub white_texture: ugli::Texture,
+    pub white_texture: Rc<ugli::Texture>,
 }
 
 #[derive(geng::asset::Load)]
@@ -31,40 +36,148 @@ struct Assets {
     pub digits: Vec<ugli::Texture>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Light {
+    /// Direction the light shines in, same everywhere like the sun
+    Directional(vec3<f32>),
+    /// Point light that follows the player, or the camera once the player is dead
+    HeadLamp {
+        offset: vec3<f32>,
+        /// Distance at which the light fades out completely
+        range: f32,
+    },
+}
+
+/// Only the tube and thick sprites are lit, flat sprites are drawn as is
+#[derive(Deserialize, Clone)]
+pub struct Lighting {
+    /// Light reaching surfaces facing away from the light
+    pub ambient: f32,
+    pub diffuse: f32,
+    pub light: Light,
+    /// Extra light on surfaces seen at a grazing angle, outlining the shapes
+    pub rim: f32,
+    /// Higher values make the rim thinner
+    pub rim_power: f32,
+}
+
+/// Player shadow, as if cast by a light on the tube axis behind the player.
+///
+/// Distances across the tube are fractions of the tube radius, so changing it keeps the shadow right
+#[derive(Deserialize, Clone)]
+pub struct Shadow {
+    /// Anything at least this far from the axis is the wall
+    pub wall: f32,
+    /// Distance of the player from the axis at which the shadow on the wall appears
+    pub wall_start: f32,
+    /// Distance at which the shadow on the wall is the darkest
+    pub wall_end: f32,
+    pub wall_darkness: f32,
+    /// Distance ahead of the player at which obstacles start getting the shadow
+    pub obstacle_start: f32,
+    /// Darkness of the shadow on obstacles right ahead of the player
+    pub obstacle_darkness: f32,
+    /// Distance ahead at which the shadow on obstacles fades out
+    pub obstacle_range: f32,
+    /// Size of the shadow at that distance, in player radii
+    pub obstacle_spread: f32,
+}
+
+#[derive(Deserialize, Clone)]
 pub struct Config {
     pub cylinder_segments: usize,
     pub fog_distance: f32,
     pub fog_color: Rgba<f32>,
+    pub lighting: Lighting,
+    pub shadow: Shadow,
+}
+
+impl validation::Validate for Lighting {
+    fn validate(&self, v: &mut validation::Validator) {
+        v.non_negative("ambient", self.ambient);
+        v.non_negative("diffuse", self.diffuse);
+        v.non_negative("rim", self.rim);
+        v.positive("rim_power", self.rim_power);
+        match &self.light {
+            Light::Directional(direction) => {
+                let ok = direction.len() > 0.0;
+                v.check("light.directional", ok, "direction must not be zero");
+            }
+            Light::HeadLamp { range, .. } => v.positive("light.head_lamp.range", *range),
+        }
+    }
+}
+
+impl validation::Validate for Shadow {
+    fn validate(&self, v: &mut validation::Validator) {
+        v.check(
+            "wall",
+            self.wall > 0.0 && self.wall <= 1.0,
+            format_args!("must be in (0, 1], got {}", self.wall),
+        );
+        v.check(
+            "wall_start",
+            self.wall_start < self.wall_end,
+            format_args!(
+                "must be less than wall_end ({} >= {})",
+                self.wall_start, self.wall_end,
+            ),
+        );
+        for (key, darkness) in [
+            ("wall_darkness", self.wall_darkness),
+            ("obstacle_darkness", self.obstacle_darkness),
+        ] {
+            v.check(
+                key,
+                (0.0..=1.0).contains(&darkness),
+                format_args!("must be from 0 to 1, got {darkness}"),
+            );
+        }
+        v.non_negative("obstacle_start", self.obstacle_start);
+        v.positive("obstacle_range", self.obstacle_range);
+        v.check(
+            "obstacle_spread",
+            self.obstacle_spread >= 1.0,
+            format_args!("must be at least 1, got {}", self.obstacle_spread),
+        );
+    }
+}
+
+impl validation::Validate for Config {
+    fn validate(&self, v: &mut validation::Validator) {
+        v.check(
+            "cylinder_segments",
+            self.cylinder_segments >= 3,
+            format_args!("must be at least 3, got {}", self.cylinder_segments),
+        );
+        v.positive("fog_distance", self.fog_distance);
+        v.nested("lighting", &self.lighting);
+        v.nested("shadow", &self.shadow);
+    }
+}
+
+/// Transforms normals along with the model matrix, even when it scales unevenly
+fn normal_matrix(model_matrix: mat4<f32>) -> mat3<f32> {
+    let m = model_matrix.inverse().transpose();
+    mat3::new([
+        [m[(0, 0)], m[(0, 1)], m[(0, 2)]],
+        [m[(1, 0)], m[(1, 1)], m[(1, 2)]],
+        [m[(2, 0)], m[(2, 1)], m[(2, 2)]],
+    ])
 }
 
 impl Render {
-    pub async fn init(geng: &Geng) -> Self {
-        let config: Config = file::load_detect(run_dir().join("assets").join("render.toml"))
+    pub async fn init(geng: &Geng, overrides: &Overrides, tube_radius: f32) -> Self {
+        let config: Config = overrides
+            .load(ConfigFile::Render)
             .await
-            .unwrap();
+            .unwrap_or_else(|e| panic!("{e}"));
         let assets: Assets = geng
             .asset_manager()
             .load(run_dir().join("assets"))
             .await
             .unwrap();
-        let cylinder = ugli::VertexBuffer::new_static(
-            geng.ugli(),
-            (0..=config.cylinder_segments)
-                .flat_map(|i| {
-                    let x = i as f32 / config.cylinder_segments as f32;
-                    let angle = Angle::from_degrees(360.0 * x);
-                    let (sin, cos) = angle.sin_cos();
-                    let pos = vec2(sin, cos);
-                    let at_z = |z: f32| Vertex {
-                        a_pos: pos.extend(z),
-                        a_uv: vec2(x, z),
-                        a_normal: vec3::UNIT_Z, // TODO maybe?
-                    };
-                    [at_z(0.0), at_z(1.0)]
-                })
-                .collect(),
-        );
         let quad = ugli::VertexBuffer::new_static(
             geng.ugli(),
             [(0, 0), (0, 1), (1, 1), (1, 0)]
@@ -72,17 +185,107 @@ impl Render {
                 .map(|(x, y)| Vertex {
                     a_pos: vec2(x, y).map(|x| x as f32 * 2.0 - 1.0).extend(0.0),
                     a_uv: vec2(x, y).map(|x| x as f32),
-                    a_normal: vec3::UNIT_Z, // TODO maybe?
+                    a_normal: vec3::UNIT_Z,
                 })
                 .collect(),
         );
         Self {
             player: Cell::new(None),
+            tube_radius,
+            cylinder: Rc::new(Self::create_cylinder(geng, &config)),
+            quad: Rc::new(quad),
+            assets: Rc::new(assets),
+            config,
+            white_texture: Rc::new(ugli::Texture::new_with(geng.ugli(), vec2::splat(1), |_| {
+                Rgba::WHITE
+            })),
+        }
+    }
+
+    /// Reread render.toml, reusing loaded resources
+    pub async fn reload(
+        &self,
+        geng: &Geng,
+        overrides: &Overrides,
+        tube_radius: f32,
+    ) -> anyhow::Result<Self> {
+        let config: Config = overrides.load(ConfigFile::Render).await?;
+        let cylinder = if config.cylinder_segments == self.config.cylinder_segments {
+            self.cylinder.clone()
+        } else {
+            Rc::new(Self::create_cylinder(geng, &config))
+        };
+        Ok(Self {
+            player: Cell::new(self.player.get()),
+            tube_radius,
             cylinder,
-            quad,
-            assets,
+            quad: self.quad.clone(),
+            assets: self.assets.clone(),
             config,
-            white_texture: ugli::Texture::new_with(geng.ugli(), vec2::splat(1), |_| Rgba::WHITE),
+            white_texture: self.white_texture.clone(),
+        })
+    }
+
+    fn create_cylinder(geng: &Geng, config: &Config) -> ugli::VertexBuffer<Vertex> {
+        ugli::VertexBuffer::new_static(
+            geng.ugli(),
+            (0..=config.cylinder_segments)
+                .flat_map(|i| {
+                    let x = i as f32 / config.cylinder_segments as f32;
+                    let angle = Angle::from_degrees(360.0 * x);
+                    let (sin, cos) = angle.sin_cos();
+                    let pos = vec2(sin, cos);
+                    let at_z = |z: f32| Vertex {
+                        a_pos: pos.extend(z),
+                        a_uv: vec2(x, z),
+                        // The tube is only ever seen from the inside
+                        a_normal: (-pos).extend(0.0),
+                    };
+                    [at_z(0.0), at_z(1.0)]
+                })
+                .collect(),
+        )
+    }
+
+    /// Flat light for anything unlit, so the same shader works for everything
+    fn light_uniforms(&self, camera: &dyn AbstractCamera3d, lit: bool) -> impl ugli::Uniforms {
+        let config = &self.config.lighting;
+        // Homogeneous, w is 0 for a direction towards the light and 1 for its position
+        let (light, range) = match config.light {
+            Light::Directional(direction) => ((-direction.normalize()).extend(0.0), 1.0),
+            Light::HeadLamp { offset, range } => {
+                let pos = match self.player.get() {
+                    Some((pos, _)) => pos,
+                    None => (camera.view_matrix().inverse() * vec4(0.0, 0.0, 0.0, 1.0)).into_3d(),
+                };
+                ((pos + offset).extend(1.0), range)
+            }
+        };
+        ugli::uniforms! {
+            u_ambient: if lit { config.ambient } else { 1.0 },
+            u_diffuse: if lit { config.diffuse } else { 0.0 },
+            u_light: light,
+            u_light_range: range,
+            u_rim: if lit { config.rim } else { 0.0 },
+            u_rim_power: config.rim_power,
+        }
+    }
+
+    fn shadow_uniforms(&self) -> impl ugli::Uniforms {
+        let config = &self.config.shadow;
+        let tube_radius = self.tube_radius;
+        let (player_pos, player_radius) = self.player.get().unwrap_or((vec3::ZERO, 0.0));
+        ugli::uniforms! {
+            u_player_pos: player_pos,
+            u_player_radius: player_radius,
+            u_wall_radius: config.wall * tube_radius,
+            u_wall_shadow_start: config.wall_start * tube_radius,
+            u_wall_shadow_end: config.wall_end * tube_radius,
+            u_wall_shadow_darkness: config.wall_darkness,
+            u_obstacle_shadow_start: config.obstacle_start,
+            u_obstacle_shadow_darkness: config.obstacle_darkness,
+            u_obstacle_shadow_range: config.obstacle_range,
+            u_obstacle_shadow_spread: config.obstacle_spread,
         }
     }
 
@@ -125,7 +328,6 @@ impl Render {
     ) {
         let matrix = matrix * mat4::scale_uniform(2.0);
         let framebuffer_size = framebuffer.size().map(|x| x as f32);
-        let (player_pos, player_radius) = self.player.get().unwrap_or((vec3::ZERO, 0.0));
         ugli::draw(
             framebuffer,
             &self.assets.shaders.texture,
@@ -140,10 +342,11 @@ impl Render {
                     u_fog_color: self.config.fog_color,
                     u_fog_distance: self.config.fog_distance,
                     u_color: Rgba::WHITE,
-                    u_player_pos: player_pos,
-                    u_player_radius: player_radius,
+                    u_normal_matrix: normal_matrix(matrix),
                 },
                 camera.uniforms(framebuffer_size),
+                self.light_uniforms(camera, true),
+                self.shadow_uniforms(),
             ),
             ugli::DrawParameters {
                 // blend_mode: Some(ugli::BlendMode::straight_alpha()),
@@ -158,7 +361,7 @@ impl Render {
             framebuffer,
             &self.assets.shaders.color_overlay,
             ugli::DrawMode::TriangleFan,
-            &self.quad,
+            &*self.quad,
             ugli::uniforms! {
                 u_color: color,
             },
@@ -180,12 +383,11 @@ impl Render {
         depth_test: bool,
     ) {
         let framebuffer_size = framebuffer.size().map(|x| x as f32);
-        let (player_pos, player_radius) = self.player.get().unwrap_or((vec3::ZERO, 0.0));
         ugli::draw(
             framebuffer,
             &self.assets.shaders.texture,
             ugli::DrawMode::TriangleFan,
-            &self.quad,
+            &*self.quad,
             (
                 ugli::uniforms! {
                     u_texture: texture,
@@ -195,10 +397,12 @@ impl Render {
                     u_fog_color: self.config.fog_color,
                     u_fog_distance: self.config.fog_distance,
                     u_color: color,
-                    u_player_pos: player_pos,
-                    u_player_radius: player_radius,
+                    // Unlit, and the matrix may well scale to nothing
+                    u_normal_matrix: mat3::identity(),
                 },
                 camera.uniforms(framebuffer_size),
+                self.light_uniforms(camera, false),
+                self.shadow_uniforms(),
             ),
             ugli::DrawParameters {
                 blend_mode: Some(ugli::BlendMode::straight_alpha()),
@@ -235,13 +439,12 @@ impl Render {
         model_matrix: mat4<f32>,
         uv_matrix: mat3<f32>,
     ) {
-        let (player_pos, player_radius) = self.player.get().unwrap_or((vec3::ZERO, 0.0));
         let framebuffer_size = framebuffer.size().map(|x| x as f32);
         ugli::draw(
             framebuffer,
             &self.assets.shaders.texture,
             ugli::DrawMode::TriangleStrip,
-            &self.cylinder,
+            &*self.cylinder,
             (
                 ugli::uniforms! {
                     u_texture: texture,
@@ -251,10 +454,11 @@ impl Render {
                     u_fog_color: self.config.fog_color,
                     u_fog_distance: self.config.fog_distance,
                     u_color: Rgba::WHITE,
-                    u_player_pos: player_pos,
-                    u_player_radius: player_radius,
+                    u_normal_matrix: normal_matrix(model_matrix),
                 },
                 camera.uniforms(framebuffer_size),
+                self.light_uniforms(camera, true),
+                self.shadow_uniforms(),
             ),
             ugli::DrawParameters {
                 blend_mode: Some(ugli::BlendMode::straight_alpha()),
@@ -0,0 +1,223 @@
+use super::*;
+
+use difficulty::Difficulty;
+use settings::Settings;
+use std::collections::{HashMap, HashSet};
+
+/// Current schema version, older saves are migrated on load
+pub const VERSION: u32 = 2;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub games_played: u64,
+    pub coins_collected: u64,
+    /// Sum of the scores of all finished games
+    pub total_score: u64,
+}
+
+/// Everything that persists between sessions
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    /// Zero means nothing was saved yet, progress is imported from the loose keys used before
+    pub version: u32,
+    pub money: u64,
+    pub best_scores: HashMap<Difficulty, i32>,
+    pub stats: Stats,
+    pub settings: Settings,
+    /// Ids of unlocked items
+    pub unlocks: HashSet<String>,
+    /// Tokens of purchases that were credited but maybe not consumed yet, so they are not credited twice
+    pub credited_purchases: HashSet<String>,
+    /// When the money last changed, in seconds since the unix epoch
+    pub updated_at: f64,
+    /// When the settings last changed
+    pub settings_updated_at: f64,
+}
+
+/// Seconds since the unix epoch
+pub fn now() -> f64 {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            use wasm_bindgen::prelude::*;
+            #[wasm_bindgen]
+            extern "C" {
+                #[wasm_bindgen(js_namespace = Date, js_name = now)]
+                fn date_now() -> f64;
+            }
+            date_now() / 1000.0
+        } else {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0.0, |time| time.as_secs_f64())
+        }
+    }
+}
+
+impl SaveData {
+    pub fn migrate(&mut self) {
+        if self.version > VERSION {
+            log::warn!(
+                "save version {} is newer than supported {VERSION}, some progress may be lost",
+                self.version,
+            );
+        }
+        while self.version < VERSION {
+            match self.version {
+                0 => self.import_legacy(),
+                // Timestamps were added, zero loses to anything saved since
+                1 => {}
+                _ => unreachable!(),
+            }
+            self.version += 1;
+        }
+    }
+
+    /// Keys used before the unified save, they are left in place for older builds.
+    ///
+    /// Only the money and a single best score were kept back then, there were no difficulties yet
+    fn import_legacy(&mut self) {
+        if let Some(money) = preferences::load("money") {
+            self.money = money;
+        }
+        if let Some(score) = preferences::load("best_score") {
+            self.best_scores.insert(Difficulty::Normal, score);
+        }
+    }
+
+    /// Combine progress from different storages, e.g. after playing on another device.
+    ///
+    /// Counters take the max, unlocks and credited purchases are joined,
+    /// money and settings come from the latest change
+    pub fn merge(self, other: Self) -> Self {
+        let (older, newer) = if other.updated_at > self.updated_at
+            || other.updated_at == self.updated_at && other.money > self.money
+        {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let settings_updated_at = older.settings_updated_at.max(newer.settings_updated_at);
+        let settings = if older.settings_updated_at > newer.settings_updated_at {
+            older.settings
+        } else {
+            newer.settings
+        };
+        let mut best_scores = older.best_scores;
+        for (difficulty, score) in newer.best_scores {
+            let best = best_scores.entry(difficulty).or_insert(score);
+            *best = (*best).max(score);
+        }
+        Self {
+            version: VERSION,
+            money: newer.money,
+            best_scores,
+            stats: Stats {
+                games_played: older.stats.games_played.max(newer.stats.games_played),
+                coins_collected: older.stats.coins_collected.max(newer.stats.coins_collected),
+                total_score: older.stats.total_score.max(newer.stats.total_score),
+            },
+            settings,
+            unlocks: older.unlocks.union(&newer.unlocks).cloned().collect(),
+            credited_purchases: older
+                .credited_purchases
+                .union(&newer.credited_purchases)
+                .cloned()
+                .collect(),
+            updated_at: newer.updated_at,
+            settings_updated_at,
+        }
+    }
+
+    pub fn best_score(&self, difficulty: Difficulty) -> i32 {
+        self.best_scores.get(&difficulty).copied().unwrap_or(0)
+    }
+
+    /// Returns whether this is a new record
+    pub fn update_best_score(&mut self, difficulty: Difficulty, score: i32) -> bool {
+        if score > self.best_score(difficulty) {
+            self.best_scores.insert(difficulty, score);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save(money: u64, updated_at: f64) -> SaveData {
+        SaveData {
+            version: VERSION,
+            money,
+            updated_at,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn migrate_v1_keeps_progress() {
+        let mut save = SaveData {
+            version: 1,
+            money: 100,
+            best_scores: HashMap::from([(Difficulty::Hard, 42)]),
+            ..default()
+        };
+        save.migrate();
+        assert_eq!(save.version, VERSION);
+        assert_eq!(save.money, 100);
+        assert_eq!(save.best_score(Difficulty::Hard), 42);
+        assert_eq!(save.updated_at, 0.0);
+    }
+
+    #[test]
+    fn merge_takes_max_counters_and_joins_unlocks() {
+        let mut a = save(0, 1.0);
+        a.best_scores = HashMap::from([(Difficulty::Normal, 10), (Difficulty::Hard, 5)]);
+        a.stats.games_played = 3;
+        a.stats.total_score = 100;
+        a.unlocks.insert("a".to_owned());
+        a.credited_purchases.insert("token".to_owned());
+        let mut b = save(0, 2.0);
+        b.best_scores = HashMap::from([(Difficulty::Normal, 7), (Difficulty::Relaxed, 1)]);
+        b.stats.games_played = 2;
+        b.stats.coins_collected = 50;
+        b.unlocks.insert("b".to_owned());
+
+        let merged = a.merge(b);
+        assert_eq!(merged.best_score(Difficulty::Normal), 10);
+        assert_eq!(merged.best_score(Difficulty::Hard), 5);
+        assert_eq!(merged.best_score(Difficulty::Relaxed), 1);
+        assert_eq!(merged.stats.games_played, 3);
+        assert_eq!(merged.stats.coins_collected, 50);
+        assert_eq!(merged.stats.total_score, 100);
+        assert_eq!(
+            merged.unlocks,
+            HashSet::from(["a".to_owned(), "b".to_owned()]),
+        );
+        assert!(merged.credited_purchases.contains("token"));
+    }
+
+    #[test]
+    fn merge_takes_latest_money_and_settings() {
+        let mut a = save(500, 2.0);
+        a.settings.difficulty = Difficulty::Hard;
+        a.settings_updated_at = 1.0;
+        let mut b = save(100, 1.0);
+        b.settings.difficulty = Difficulty::Relaxed;
+        b.settings_updated_at = 3.0;
+
+        let merged = a.clone().merge(b.clone());
+        assert_eq!(merged.money, 500);
+        assert_eq!(merged.updated_at, 2.0);
+        assert_eq!(merged.settings.difficulty, Difficulty::Relaxed);
+        assert_eq!(merged.settings_updated_at, 3.0);
+
+        let merged = b.merge(a);
+        assert_eq!(merged.money, 500);
+        assert_eq!(merged.settings.difficulty, Difficulty::Relaxed);
+    }
+}
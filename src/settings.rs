@@ -0,0 +1,26 @@
+use super::*;
+
+use difficulty::Difficulty;
+use std::collections::HashSet;
+
+/// Player choices that persist between sessions
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Last chosen difficulty
+    pub difficulty: Difficulty,
+    /// Packs are enabled unless turned off
+    pub disabled_obstacle_packs: HashSet<String>,
+}
+
+impl Settings {
+    pub fn obstacle_pack_enabled(&self, pack: &str) -> bool {
+        !self.disabled_obstacle_packs.contains(pack)
+    }
+
+    pub fn toggle_obstacle_pack(&mut self, pack: &str) {
+        if !self.disabled_obstacle_packs.remove(pack) {
+            self.disabled_obstacle_packs.insert(pack.to_owned());
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use super::*;
+
+use base64::Engine as _;
+use difficulty::Difficulty;
+use save::SaveData;
+use std::collections::{HashMap, HashSet};
+
+const PREFIX: &str = "MJ";
+/// Bumped when [Shared] changes incompatibly
+const VERSION: u32 = 1;
+
+/// Progress that moves between devices with a code, settings stay where they are
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shared {
+    pub money: u64,
+    pub best_scores: HashMap<Difficulty, i32>,
+    pub unlocks: HashSet<String>,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    NotACode,
+    Checksum,
+    NewerVersion(u32),
+    Data(serde_json::Error),
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotACode => write!(f, "not a save code"),
+            Self::Checksum => write!(f, "code is damaged, check that it was copied fully"),
+            Self::NewerVersion(version) => {
+                write!(f, "code is from a newer version of the game ({version})")
+            }
+            Self::Data(e) => write!(f, "code has invalid data: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl ImportError {
+    /// Message shown to the player, without the details
+    pub fn locale_key(&self) -> &'static str {
+        match self {
+            Self::NotACode => "import_not_a_code",
+            Self::Checksum => "import_damaged",
+            Self::NewerVersion(_) => "import_newer_version",
+            Self::Data(_) => "import_invalid",
+        }
+    }
+}
+
+/// FNV-1a, only meant to catch typos and truncated copies
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c9dc5, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+impl Shared {
+    pub fn new(save: &SaveData) -> Self {
+        Self {
+            money: save.money,
+            best_scores: save.best_scores.clone(),
+            unlocks: save.unlocks.clone(),
+        }
+    }
+
+    /// Merged into the save the same way [SaveData::merge] joins devices,
+    /// so the next cloud load keeps it: money is replaced as the latest change,
+    /// best scores only go up and unlocks are added
+    pub fn apply(self, save: &mut SaveData) {
+        save.money = self.money;
+        save.updated_at = save::now();
+        for (difficulty, score) in self.best_scores {
+            save.update_best_score(difficulty, score);
+        }
+        save.unlocks.extend(self.unlocks);
+    }
+
+    /// Looks like `MJ1-<base64 of json and checksum>`
+    pub fn export(&self) -> String {
+        let mut data = serde_json::to_vec(self).unwrap();
+        data.extend(checksum(&data).to_be_bytes());
+        format!(
+            "{PREFIX}{VERSION}-{}",
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data),
+        )
+    }
+
+    pub fn import(code: &str) -> Result<Self, ImportError> {
+        let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+        let (version, data) = code
+            .strip_prefix(PREFIX)
+            .and_then(|code| code.split_once('-'))
+            .ok_or(ImportError::NotACode)?;
+        let version: u32 = version.parse().map_err(|_| ImportError::NotACode)?;
+        if version > VERSION {
+            return Err(ImportError::NewerVersion(version));
+        }
+        let data = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(data)
+            .map_err(|_| ImportError::Checksum)?;
+        if data.len() < 4 {
+            return Err(ImportError::Checksum);
+        }
+        let (data, sum) = data.split_at(data.len() - 4);
+        if checksum(data).to_be_bytes() != sum {
+            return Err(ImportError::Checksum);
+        }
+        serde_json::from_slice(data).map_err(ImportError::Data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shared() -> Shared {
+        Shared {
+            money: 120,
+            best_scores: HashMap::from([(Difficulty::Normal, 300), (Difficulty::Hard, 50)]),
+            unlocks: HashSet::from(["skin_cat".to_owned()]),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let code = shared().export();
+        assert!(code.starts_with("MJ1-"));
+        // Line breaks and spaces from copying are ignored
+        let (head, tail) = code.split_at(code.len() / 2);
+        let imported = Shared::import(&format!(" {head}\n{tail} ")).unwrap();
+        assert_eq!(imported.money, 120);
+        assert_eq!(imported.best_scores, shared().best_scores);
+        assert_eq!(imported.unlocks, shared().unlocks);
+    }
+
+    #[test]
+    fn corrupted_checksum() {
+        let mut code = shared().export().into_bytes();
+        let index = code.len() / 2;
+        code[index] = if code[index] == b'A' { b'B' } else { b'A' };
+        let code = String::from_utf8(code).unwrap();
+        assert!(matches!(Shared::import(&code), Err(ImportError::Checksum)));
+    }
+
+    #[test]
+    fn truncated_code() {
+        let code = shared().export();
+        for len in [code.len() - 3, "MJ1-".len() + 2, "MJ1-".len()] {
+            assert!(
+                matches!(Shared::import(&code[..len]), Err(ImportError::Checksum)),
+                "{len}",
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_prefix() {
+        let code = shared().export().replacen("MJ1-", "XY1-", 1);
+        assert!(matches!(Shared::import(&code), Err(ImportError::NotACode)));
+        assert!(matches!(
+            Shared::import("hello"),
+            Err(ImportError::NotACode)
+        ));
+    }
+
+    #[test]
+    fn newer_version() {
+        let code = shared().export().replacen("MJ1-", "MJ2-", 1);
+        assert!(matches!(
+            Shared::import(&code),
+            Err(ImportError::NewerVersion(2))
+        ));
+    }
+
+    #[test]
+    fn apply_survives_cloud_merge() {
+        let cloud = SaveData {
+            version: save::VERSION,
+            money: 500,
+            best_scores: HashMap::from([(Difficulty::Normal, 1000)]),
+            unlocks: HashSet::from(["skin_dog".to_owned()]),
+            updated_at: 1.0,
+            ..default()
+        };
+        let mut save = cloud.clone();
+        shared().apply(&mut save);
+        assert_eq!(save.money, 120);
+        assert_eq!(save.best_score(Difficulty::Normal), 1000);
+        assert_eq!(save.best_score(Difficulty::Hard), 50);
+        assert_eq!(save.unlocks.len(), 2);
+
+        let merged = save.clone().merge(cloud);
+        assert_eq!(merged.money, save.money);
+        assert_eq!(merged.best_scores, save.best_scores);
+        assert_eq!(merged.unlocks, save.unlocks);
+    }
+}
@@ -0,0 +1,108 @@
+use super::*;
+
+use config::ShopReward;
+use platform::{Payments, Purchase, PurchaseError};
+use save::SaveData;
+use std::collections::HashSet;
+
+/// Gives what the product is worth, false if the config does not know the product
+pub fn credit(config: &config::Shop, save: &mut SaveData, product_id: &str) -> bool {
+    let Some(reward) = config.products.get(product_id) else {
+        log::error!("no reward for product {product_id:?}");
+        return false;
+    };
+    match reward {
+        ShopReward::Coins(coins) => {
+            save.money += coins;
+            save.updated_at = save::now();
+        }
+        ShopReward::Unlock(id) => {
+            save.unlocks.insert(id.clone());
+        }
+    }
+    true
+}
+
+/// Buying the product again would give nothing, like an already unlocked skin
+pub fn owned(config: &config::Shop, save: &SaveData, product_id: &str) -> bool {
+    match config.products.get(product_id) {
+        Some(ShopReward::Unlock(id)) => save.unlocks.contains(id),
+        _ => false,
+    }
+}
+
+/// The purchase is only consumed once the reward is saved,
+/// so closing the game in between credits it on the next start instead of losing it.
+///
+/// The token is saved with the reward, so failing to consume does not credit it again
+async fn credit_and_consume(
+    config: &config::Shop,
+    storage: &storage::Storage,
+    payments: &dyn Payments,
+    save: &mut SaveData,
+    purchase: &Purchase,
+) -> anyhow::Result<()> {
+    if save.credited_purchases.contains(&purchase.token) {
+        log::info!("purchase of {} was already credited", purchase.product_id);
+    } else {
+        if !credit(config, save, &purchase.product_id) {
+            // Left unconsumed for a version that knows the product
+            anyhow::bail!("unknown product {:?}", purchase.product_id);
+        }
+        save.credited_purchases.insert(purchase.token.clone());
+        storage.save(save).await;
+    }
+    payments.consume(purchase).await
+}
+
+pub async fn buy(ctx: &Ctx, save: &mut SaveData, product_id: &str) -> Result<(), PurchaseError> {
+    let payments = ctx
+        .platform
+        .payments()
+        .ok_or_else(|| PurchaseError::Failed(anyhow::anyhow!("payments are unavailable")))?;
+    let purchase = payments.purchase(product_id).await?;
+    credit_and_consume(&ctx.config.shop, &ctx.storage, payments, save, &purchase)
+        .await
+        .map_err(PurchaseError::Failed)
+}
+
+/// Credit purchases that were paid for but never consumed, like when the game was closed while paying
+pub async fn process_unconsumed(ctx: &Ctx, save: SaveData) -> SaveData {
+    match ctx.platform.payments() {
+        Some(payments) => credit_unconsumed(&ctx.config.shop, &ctx.storage, payments, save).await,
+        None => save,
+    }
+}
+
+/// [process_unconsumed] with the parts of the context it needs
+pub async fn credit_unconsumed(
+    config: &config::Shop,
+    storage: &storage::Storage,
+    payments: &dyn Payments,
+    mut save: SaveData,
+) -> SaveData {
+    let purchases = match payments.unconsumed().await {
+        Ok(purchases) => purchases,
+        Err(e) => {
+            log::error!("failed to get unconsumed purchases: {e:#}");
+            return save;
+        }
+    };
+    let mut still_unconsumed = HashSet::new();
+    for purchase in &purchases {
+        match credit_and_consume(config, storage, payments, &mut save, purchase).await {
+            Ok(()) => log::info!("consumed purchase of {}", purchase.product_id),
+            Err(e) => {
+                log::error!(
+                    "failed to process purchase of {}: {e:#}",
+                    purchase.product_id
+                );
+                still_unconsumed.insert(purchase.token.clone());
+            }
+        }
+    }
+    // Consumed purchases never come back, their tokens are not needed anymore
+    save.credited_purchases
+        .retain(|token| still_unconsumed.contains(token));
+    save
+}
@@ -0,0 +1,288 @@
+use super::*;
+
+use save::SaveData;
+
+/// Seconds before the first retry of a failed save, doubled after every failure
+const RETRY_DELAY: f64 = 5.0;
+const MAX_RETRY_DELAY: f64 = 60.0;
+
+/// Somewhere progress can be kept
+pub trait Backend {
+    fn name(&self) -> &str;
+    /// None if nothing was saved here yet
+    fn load(&self) -> future::LocalBoxFuture<'_, anyhow::Result<Option<SaveData>>>;
+    fn save<'a>(&'a self, save: &'a SaveData) -> future::LocalBoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// Browser local storage or a file next to the executable
+pub struct Local;
+
+impl Local {
+    const KEY: &'static str = "save";
+
+    /// Progress saved on this device, imported from the old loose keys if there is none yet
+    pub fn read() -> SaveData {
+        let mut save: SaveData = preferences::load(Self::KEY).unwrap_or_default();
+        save.migrate();
+        save
+    }
+}
+
+impl Backend for Local {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn load(&self) -> future::LocalBoxFuture<'_, anyhow::Result<Option<SaveData>>> {
+        future::ready(Ok(Some(Self::read()))).boxed_local()
+    }
+
+    fn save<'a>(&'a self, save: &'a SaveData) -> future::LocalBoxFuture<'a, anyhow::Result<()>> {
+        preferences::save(Self::KEY, save);
+        future::ready(Ok(())).boxed_local()
+    }
+}
+
+/// Progress that is lost on exit
+#[derive(Default)]
+pub struct Memory {
+    data: RefCell<Option<SaveData>>,
+}
+
+impl Backend for Memory {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn load(&self) -> future::LocalBoxFuture<'_, anyhow::Result<Option<SaveData>>> {
+        future::ready(Ok(self.data.borrow().clone())).boxed_local()
+    }
+
+    fn save<'a>(&'a self, save: &'a SaveData) -> future::LocalBoxFuture<'a, anyhow::Result<()>> {
+        *self.data.borrow_mut() = Some(save.clone());
+        future::ready(Ok(())).boxed_local()
+    }
+}
+
+struct Entry {
+    backend: Box<dyn Backend>,
+    /// Last save failed and has to be retried
+    failed: Cell<bool>,
+}
+
+struct Retry {
+    timer: Timer,
+    delay: f64,
+}
+
+/// Saves to every backend, merging what they have on load
+pub struct Storage {
+    entries: Vec<Entry>,
+    retry: RefCell<Option<Retry>>,
+}
+
+impl Storage {
+    pub fn new(backends: Vec<Box<dyn Backend>>) -> Self {
+        Self {
+            entries: backends
+                .into_iter()
+                .map(|backend| Entry {
+                    backend,
+                    failed: Cell::new(false),
+                })
+                .collect(),
+            retry: RefCell::new(None),
+        }
+    }
+
+    /// Backends that fail to load are skipped
+    pub async fn load(&self) -> SaveData {
+        let mut result: Option<SaveData> = None;
+        for entry in &self.entries {
+            match entry.backend.load().await {
+                Ok(Some(save)) => {
+                    result = Some(match result {
+                        Some(result) => result.merge(save),
+                        None => save,
+                    });
+                }
+                Ok(None) => {}
+                Err(e) => log::error!("failed to load {} save: {e:#}", entry.backend.name()),
+            }
+        }
+        result.unwrap_or_else(|| SaveData {
+            version: save::VERSION,
+            ..default()
+        })
+    }
+
+    /// Failed backends are retried with the latest save in [Self::retry]
+    pub async fn save(&self, save: &SaveData) {
+        for entry in &self.entries {
+            self.save_to(entry, save).await;
+        }
+        self.schedule_retry(false);
+    }
+
+    /// Some backends failed and it is time to save to them again
+    pub fn retry_due(&self) -> bool {
+        self.retry.borrow().as_ref().map_or(false, |retry| {
+            retry.timer.elapsed().as_secs_f64() > retry.delay
+        })
+    }
+
+    /// Saves again to the backends that failed once it is [Self::retry_due]
+    pub async fn retry(&self, save: &SaveData) {
+        if !self.retry_due() {
+            return;
+        }
+        for entry in &self.entries {
+            if entry.failed.get() {
+                self.save_to(entry, save).await;
+            }
+        }
+        self.schedule_retry(true);
+    }
+
+    async fn save_to(&self, entry: &Entry, save: &SaveData) {
+        match entry.backend.save(save).await {
+            Ok(()) => entry.failed.set(false),
+            Err(e) => {
+                log::error!("failed to save to {}: {e:#}", entry.backend.name());
+                entry.failed.set(true);
+            }
+        }
+    }
+
+    fn schedule_retry(&self, backoff: bool) {
+        let mut retry = self.retry.borrow_mut();
+        if !self.entries.iter().any(|entry| entry.failed.get()) {
+            *retry = None;
+        } else if let Some(pending) = &mut *retry {
+            if backoff {
+                pending.timer = Timer::new();
+                pending.delay = (pending.delay * 2.0).min(MAX_RETRY_DELAY);
+            }
+        } else {
+            *retry = Some(Retry {
+                timer: Timer::new(),
+                delay: RETRY_DELAY,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+
+    /// Fails to save while `failing` is set
+    struct Flaky {
+        failing: Rc<Cell<bool>>,
+        saved: Rc<RefCell<Option<SaveData>>>,
+    }
+
+    impl Backend for Flaky {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn load(&self) -> future::LocalBoxFuture<'_, anyhow::Result<Option<SaveData>>> {
+            future::ready(Ok(self.saved.borrow().clone())).boxed_local()
+        }
+
+        fn save<'a>(
+            &'a self,
+            save: &'a SaveData,
+        ) -> future::LocalBoxFuture<'a, anyhow::Result<()>> {
+            let result = if self.failing.get() {
+                Err(anyhow::anyhow!("offline"))
+            } else {
+                *self.saved.borrow_mut() = Some(save.clone());
+                Ok(())
+            };
+            future::ready(result).boxed_local()
+        }
+    }
+
+    fn memory(save: SaveData) -> Box<dyn Backend> {
+        Box::new(Memory {
+            data: RefCell::new(Some(save)),
+        })
+    }
+
+    #[test]
+    fn load_merges_local_and_cloud() {
+        let local = SaveData {
+            version: save::VERSION,
+            money: 10,
+            updated_at: 1.0,
+            unlocks: ["local".to_owned()].into(),
+            ..default()
+        };
+        let cloud = SaveData {
+            version: save::VERSION,
+            money: 20,
+            updated_at: 2.0,
+            unlocks: ["cloud".to_owned()].into(),
+            ..default()
+        };
+        let storage = Storage::new(vec![memory(local), memory(cloud), Box::<Memory>::default()]);
+        let save = block_on(storage.load());
+        assert_eq!(save.money, 20);
+        assert_eq!(save.unlocks.len(), 2);
+    }
+
+    #[test]
+    fn load_without_saves_is_current_version() {
+        let storage = Storage::new(vec![Box::<Memory>::default()]);
+        assert_eq!(block_on(storage.load()).version, save::VERSION);
+    }
+
+    #[test]
+    fn retry_saves_to_failed_backend() {
+        let failing = Rc::new(Cell::new(true));
+        let saved = Rc::new(RefCell::new(None));
+        let storage = Storage::new(vec![
+            Box::<Memory>::default(),
+            Box::new(Flaky {
+                failing: failing.clone(),
+                saved: saved.clone(),
+            }),
+        ]);
+        let save = SaveData {
+            money: 7,
+            ..default()
+        };
+        block_on(storage.save(&save));
+        assert!(saved.borrow().is_none());
+        assert!(storage.retry.borrow().is_some());
+
+        // Not due yet
+        failing.set(false);
+        block_on(storage.retry(&save));
+        assert!(saved.borrow().is_none());
+
+        storage.retry.borrow_mut().as_mut().unwrap().delay = 0.0;
+        block_on(storage.retry(&save));
+        assert_eq!(saved.borrow().as_ref().map(|save| save.money), Some(7));
+        assert!(storage.retry.borrow().is_none());
+    }
+
+    #[test]
+    fn retry_backs_off_while_failing() {
+        let storage = Storage::new(vec![Box::new(Flaky {
+            failing: Rc::new(Cell::new(true)),
+            saved: default(),
+        })]);
+        let save = SaveData::default();
+        block_on(storage.save(&save));
+        assert_eq!(storage.retry.borrow().as_ref().unwrap().delay, RETRY_DELAY);
+
+        storage.retry.borrow_mut().as_mut().unwrap().delay = 1e-9;
+        block_on(storage.retry(&save));
+        assert_eq!(storage.retry.borrow().as_ref().unwrap().delay, 2e-9);
+    }
+}
@@ -0,0 +1,218 @@
+use super::*;
+
+use difficulty::Difficulty;
+use overrides::{ConfigFile, Overrides};
+
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
+/// Something wrong with a config file
+#[derive(Debug)]
+pub struct Problem {
+    pub file: PathBuf,
+    /// Dotted path to the offending value, none if the whole file is broken
+    pub key: Option<String>,
+    pub message: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(key) = &self.key {
+            write!(f, ": {key}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Debug)]
+pub struct Problems(pub Vec<Problem>);
+
+impl Display for Problems {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, problem) in self.0.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Problems {}
+
+pub trait Validate {
+    fn validate(&self, v: &mut Validator);
+}
+
+pub struct Validator<'a> {
+    file: &'a Path,
+    prefix: Option<String>,
+    problems: &'a mut Vec<Problem>,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(file: &'a Path, problems: &'a mut Vec<Problem>) -> Self {
+        Self {
+            file,
+            prefix: None,
+            problems,
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("{prefix}.{key}"),
+            None => key.to_owned(),
+        }
+    }
+
+    pub fn nested(&mut self, key: &str, value: &(impl Validate + ?Sized)) {
+        value.validate(&mut Validator {
+            file: self.file,
+            prefix: Some(self.key(key)),
+            problems: self.problems,
+        });
+    }
+
+    pub fn check(&mut self, key: &str, ok: bool, message: impl Display) {
+        if !ok {
+            self.problems.push(Problem {
+                file: self.file.to_owned(),
+                key: Some(self.key(key)),
+                message: message.to_string(),
+            });
+        }
+    }
+
+    pub fn positive<T: PartialOrd + Default + Display>(&mut self, key: &str, value: T) {
+        let ok = value > T::default();
+        self.check(key, ok, format_args!("must be positive, got {value}"));
+    }
+
+    pub fn non_negative<T: PartialOrd + Default + Display>(&mut self, key: &str, value: T) {
+        let ok = value >= T::default();
+        self.check(key, ok, format_args!("must not be negative, got {value}"));
+    }
+}
+
+impl<T: PartialOrd + Display> Validate for config::MinMax<T> {
+    fn validate(&self, v: &mut Validator) {
+        v.check(
+            "min",
+            self.min <= self.max,
+            format_args!("must not be greater than max ({} > {})", self.min, self.max),
+        );
+    }
+}
+
+fn file_problem(path: &Path, e: impl Display) -> Problems {
+    Problems(vec![Problem {
+        file: path.to_owned(),
+        key: None,
+        message: format!("{e:#}"),
+    }])
+}
+
+/// Load a config file and validate it, reporting every problem found
+pub async fn load<T: DeserializeOwned + Validate>(path: impl AsRef<Path>) -> Result<T, Problems> {
+    let path = path.as_ref();
+    let value: T = file::load_detect(path)
+        .await
+        .map_err(|e| file_problem(path, e))?;
+    validate(path, value)
+}
+
+/// Same as [load], but with the layers deep merged over the file in order before deserializing
+pub async fn load_layered<T: DeserializeOwned + Validate>(
+    path: impl AsRef<Path>,
+    layers: &[toml::Table],
+) -> Result<T, Problems> {
+    let path = path.as_ref();
+    let mut table: toml::Table = file::load_detect(path)
+        .await
+        .map_err(|e| file_problem(path, e))?;
+    for layer in layers {
+        overrides::merge(&mut table, layer);
+    }
+    let value: T = toml::Value::Table(table).try_into().map_err(|e| {
+        if layers.iter().all(|layer| layer.is_empty()) {
+            file_problem(path, e)
+        } else {
+            file_problem(path, format_args!("{e} (with overrides applied)"))
+        }
+    })?;
+    validate(path, value)
+}
+
+fn validate<T: Validate>(path: &Path, value: T) -> Result<T, Problems> {
+    let mut problems = Vec::new();
+    value.validate(&mut Validator::new(path, &mut problems));
+    if !problems.is_empty() {
+        return Err(Problems(problems));
+    }
+    Ok(value)
+}
+
+fn collect<T>(result: Result<T, Problems>, problems: &mut Vec<Problem>) {
+    if let Err(Problems(file_problems)) = result {
+        problems.extend(file_problems);
+    }
+}
+
+/// Validate all the configs without starting the game
+pub async fn check_all(overrides: &Overrides) -> Vec<Problem> {
+    let assets = run_dir().join("assets");
+    let mut problems = Vec::new();
+    match difficulty::load_presets().await {
+        Ok(presets) => {
+            for difficulty in Difficulty::ALL {
+                let result = overrides.load_config(&presets[&difficulty]).await;
+                if let Err(Problems(preset_problems)) = result {
+                    problems.extend(preset_problems.into_iter().map(|problem| Problem {
+                        message: format!("{} ({} difficulty)", problem.message, difficulty.name()),
+                        ..problem
+                    }));
+                }
+            }
+        }
+        Err(e) => problems.push(Problem {
+            file: difficulty::presets_path(),
+            key: None,
+            message: format!("{e:#}"),
+        }),
+    }
+    collect(
+        overrides.load::<render::Config>(ConfigFile::Render).await,
+        &mut problems,
+    );
+    collect(
+        overrides
+            .load::<particles::Config>(ConfigFile::Particles)
+            .await,
+        &mut problems,
+    );
+    collect(
+        load::<controls::Controls>(assets.join("controls.toml")).await,
+        &mut problems,
+    );
+    let list_path = assets.join("obstacles").join("_list.ron");
+    match file::load_detect::<Vec<String>>(&list_path).await {
+        Ok(list) => {
+            for name in list {
+                let path = assets.join("obstacles").join(name).with_extension("toml");
+                collect(load::<assets::ObstacleConfig>(path).await, &mut problems);
+            }
+        }
+        Err(e) => problems.push(Problem {
+            file: list_path,
+            key: None,
+            message: format!("{e:#}"),
+        }),
+    }
+    problems.extend(locale::check_all().await);
+    problems
+}
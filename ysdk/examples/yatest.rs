@@ -1,7 +1,9 @@
 fn main() {
     batbox_logger::init();
     wasm_bindgen_futures::spawn_local(async {
-        let sdk = ysdk::Ysdk::init().await.unwrap();
+        let sdk = ysdk::Ysdk::init(std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
         log::info!("YO");
         log::info!("mobile = {:?}", sdk.device_info().is_mobile());
         log::info!("desktop = {:?}", sdk.device_info().is_desktop());
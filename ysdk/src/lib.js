@@ -3,6 +3,25 @@ export function ready(ysdk) {
     ysdk.features.LoadingAPI?.ready();
 }
 
+// Gameplay markers, the SDK uses them to know when it is fine to show ads and such
+export function gameplay_start(ysdk) {
+    ysdk.features.GameplayAPI?.start();
+}
+
+export function gameplay_stop(ysdk) {
+    ysdk.features.GameplayAPI?.stop();
+}
+
+// Interface language of Yandex Games, like `ru`
+export function environment_lang(ysdk) {
+    return ysdk.environment.i18n.lang;
+}
+
+export function on_pause_resume(ysdk, onPause, onResume) {
+    ysdk.on('game_api_pause', onPause);
+    ysdk.on('game_api_resume', onResume);
+}
+
 export function show_fullscreen_adv(ysdk, onClose, onOpen, onError, onOffline) {
     ysdk.adv.showFullscreenAdv({
         callbacks: {
@@ -14,6 +33,17 @@ export function show_fullscreen_adv(ysdk, onClose, onOpen, onError, onOffline) {
     });
 }
 
+export function show_rewarded_video(ysdk, onOpen, onRewarded, onClose, onError) {
+    ysdk.adv.showRewardedVideo({
+        callbacks: {
+            onOpen,
+            onRewarded,
+            onClose,
+            onError,
+        },
+    });
+}
+
 export function get_player(ysdk, scopes) {
     return ysdk.getPlayer({ scopes })
 }
@@ -29,4 +59,123 @@ export async function get_player_numeric_data(player, key) {
 
 export async function set_player_numeric_data(player, key, value) {
     return await player.setStats({ [key]: value });
-}
\ No newline at end of file
+}
+
+export async function set_player_stats(player, json) {
+    return await player.setStats(JSON.parse(json));
+}
+
+export async function get_player_data(player) {
+    let data = await player.getData();
+    return Object.keys(data).length === 0 ? null : JSON.stringify(data);
+}
+
+// Replaces all of the player data
+export async function set_player_data(player, json, flush) {
+    return await player.setData(JSON.parse(json), flush);
+}
+
+export function get_leaderboards(ysdk) {
+    return ysdk.getLeaderboards();
+}
+
+function leaderboard_entry(entry) {
+    let player = entry.player;
+    return {
+        score: entry.score,
+        rank: entry.rank,
+        formattedScore: entry.formattedScore,
+        extraData: entry.extraData ?? null,
+        player: {
+            uniqueId: player.uniqueID,
+            publicName: player.publicName,
+            lang: player.lang || null,
+            avatarSmall: player.getAvatarSrc('small') || null,
+            avatarMedium: player.getAvatarSrc('medium') || null,
+            avatarLarge: player.getAvatarSrc('large') || null,
+        },
+    };
+}
+
+export async function set_leaderboard_score(leaderboards, name, score, extraData) {
+    await leaderboards.setLeaderboardScore(name, score, extraData ?? undefined);
+}
+
+export async function get_leaderboard_player_entry(leaderboards, name) {
+    try {
+        return JSON.stringify(leaderboard_entry(await leaderboards.getLeaderboardPlayerEntry(name)));
+    } catch (e) {
+        if (e.code === 'LEADERBOARD_PLAYER_NOT_PRESENT') {
+            return null;
+        }
+        throw e;
+    }
+}
+
+export async function get_leaderboard_entries(leaderboards, name, includeUser, quantityAround, quantityTop) {
+    let result = await leaderboards.getLeaderboardEntries(name, {
+        includeUser,
+        quantityAround,
+        quantityTop,
+    });
+    return JSON.stringify({
+        ranges: result.ranges,
+        userRank: result.userRank,
+        entries: result.entries.map(leaderboard_entry),
+    });
+}
+
+export function get_payments(ysdk) {
+    return ysdk.getPayments({ signed: false });
+}
+
+function purchase_json(purchase) {
+    return {
+        productId: purchase.productID,
+        purchaseToken: purchase.purchaseToken,
+        developerPayload: purchase.developerPayload ?? null,
+    };
+}
+
+export async function get_catalog(payments) {
+    let products = await payments.getCatalog();
+    return JSON.stringify(products.map(product => ({
+        id: product.id,
+        title: product.title,
+        description: product.description,
+        imageUri: product.imageURI,
+        price: product.price,
+        priceValue: product.priceValue,
+        priceCurrencyCode: product.priceCurrencyCode,
+    })));
+}
+
+// Codes of the errors the SDK rejects with when the player closes the payment window
+const PURCHASE_CANCELLED_CODES = ['PURCHASE_CANCELLED', 'USER_CANCELLED'];
+
+// Errors without one of these codes are failures, even if the message mentions closing
+function purchase_cancelled(e) {
+    return PURCHASE_CANCELLED_CODES.includes(e?.code);
+}
+
+// Null if the player closed the payment window
+export async function purchase(payments, id, developerPayload) {
+    try {
+        let purchase = await payments.purchase({ id, developerPayload: developerPayload ?? undefined });
+        return JSON.stringify(purchase_json(purchase));
+    } catch (e) {
+        if (purchase_cancelled(e)) {
+            return null;
+        }
+        throw e;
+    }
+}
+
+export async function get_purchases(payments) {
+    let purchases = await payments.getPurchases();
+    return JSON.stringify(purchases.map(purchase_json));
+}
+
+export async function consume_purchase(payments, token) {
+    await payments.consumePurchase(token);
+}
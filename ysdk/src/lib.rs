@@ -1,152 +1,445 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, time::Duration};
 
 use wasm_bindgen::prelude::*;
 
-mod ysdk_sys {
-    use super::*;
+mod mock;
+#[cfg(target_arch = "wasm32")]
+mod web;
 
-    #[wasm_bindgen]
-    extern "C" {
-        pub type YaGames;
+pub use mock::{AdOutcome, Mock, PurchaseOutcome, RewardedOutcome, MOCK_PLAYER};
 
-        #[wasm_bindgen(js_namespace = YaGames)]
-        pub fn init() -> js_sys::Promise;
-
-        pub type DeviceInfo;
-
-        #[wasm_bindgen(method, getter)]
-        pub fn deviceInfo(this: &YaGames) -> DeviceInfo;
-
-        #[wasm_bindgen(method)]
-        pub fn isMobile(this: &DeviceInfo) -> bool;
-        #[wasm_bindgen(method)]
-        pub fn isDesktop(this: &DeviceInfo) -> bool;
-        #[wasm_bindgen(method)]
-        pub fn isTablet(this: &DeviceInfo) -> bool;
+/// Fails with [Error::Timeout] if the future takes longer than `timeout`, mocked calls finish right away
+pub async fn timeout<T>(
+    timeout: Duration,
+    future: impl std::future::Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    #[cfg(target_arch = "wasm32")]
+    return web::timeout(timeout, future).await;
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = timeout;
+        future.await
     }
+}
 
-    #[wasm_bindgen(module = "/src/lib.js")]
-    extern "C" {
-        pub fn ready(ysdk: &YaGames);
-        pub fn show_fullscreen_adv(
-            ysdk: &YaGames,
-            on_close: Option<js_sys::Function>,
-            on_open: Option<js_sys::Function>,
-            on_error: Option<js_sys::Function>,
-            on_offline: Option<js_sys::Function>,
-        );
-        pub fn get_player(ysdk: &YaGames, scopes: bool) -> js_sys::Promise;
-        pub fn player_unique_id(player: &JsValue) -> String;
-        pub async fn get_player_numeric_data(player: &JsValue, key: &str) -> JsValue;
-        #[wasm_bindgen(catch)]
-        pub async fn set_player_numeric_data(
-            player: &JsValue,
-            key: &str,
-            value: f64,
-        ) -> Result<JsValue, JsValue>;
-    }
+enum PlayerBackend {
+    #[cfg(target_arch = "wasm32")]
+    Web(web::Player),
+    Mock(Rc<Mock>),
 }
 
-pub struct Player(JsValue);
+pub struct Player(PlayerBackend);
 
 impl Player {
     pub fn unique_id(&self) -> String {
-        ysdk_sys::player_unique_id(&self.0)
-    }
-    pub async fn numeric_data(&self, key: &str) -> Option<f64> {
-        let result = ysdk_sys::get_player_numeric_data(&self.0, key).await;
-        if let Some(value) = result.as_f64() {
-            return Some(value);
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            PlayerBackend::Web(player) => player.unique_id(),
+            PlayerBackend::Mock(_) => MOCK_PLAYER.to_owned(),
         }
-        if result.is_null() || result.is_undefined() {
-            return None;
+    }
+    /// None if the stat was never set
+    pub async fn numeric_data(&self, key: &str) -> Result<Option<f64>, Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            PlayerBackend::Web(player) => player.numeric_data(key).await,
+            PlayerBackend::Mock(mock) => mock.numeric_data(key),
         }
-        panic!("wtf");
     }
     pub async fn set_numeric_data(&self, key: &str, value: f64) -> Result<(), Error> {
-        match ysdk_sys::set_player_numeric_data(&self.0, key, value).await {
-            Ok(value) => {
-                assert!(value.is_undefined());
-                Ok(())
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            PlayerBackend::Web(player) => player.set_numeric_data(key, value).await,
+            PlayerBackend::Mock(mock) => mock.set_numeric_data(key, value),
+        }
+    }
+    /// Sets several stats in one request, the rest are kept
+    pub async fn set_stats(&self, stats: &[(&str, f64)]) -> Result<(), Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            PlayerBackend::Web(player) => {
+                let stats: std::collections::HashMap<_, _> = stats.iter().copied().collect();
+                player.set_stats(&serde_json::to_string(&stats)?).await
+            }
+            PlayerBackend::Mock(mock) => mock.set_stats(stats),
+        }
+    }
+    /// All of the player data, None if nothing was saved yet
+    pub async fn data<T: serde::de::DeserializeOwned>(&self) -> Result<Option<T>, Error> {
+        let json = match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            PlayerBackend::Web(player) => player.data().await?,
+            PlayerBackend::Mock(mock) => mock.data()?,
+        };
+        match json {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+    /// Replaces all of the player data.
+    ///
+    /// Data is sent right away with `flush`, otherwise the SDK may delay it
+    pub async fn set_data<T: serde::Serialize>(&self, data: &T, flush: bool) -> Result<(), Error> {
+        let json = serde_json::to_string(data)?;
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            PlayerBackend::Web(player) => player.set_data(&json, flush).await,
+            PlayerBackend::Mock(mock) => {
+                let _ = flush;
+                mock.set_data(&json)
             }
-            Err(e) => Err(Error::Js(e)),
         }
     }
 }
 
-pub struct Ysdk {
-    inner: ysdk_sys::YaGames,
+enum Backend {
+    #[cfg(target_arch = "wasm32")]
+    Web(web::Ysdk),
+    Mock(Rc<Mock>),
 }
 
+pub struct Ysdk(Backend);
+
 impl Ysdk {
+    /// Loads the real SDK, fails with [Error::Unsupported] outside of the browser.
+    ///
+    /// Fails with [Error::ScriptLoad] if the script is blocked or unreachable,
+    /// and with [Error::Timeout] if loading and initializing take longer than `timeout`
+    pub async fn init(timeout: Duration) -> Result<Self, Error> {
+        #[cfg(target_arch = "wasm32")]
+        return Ok(Self(Backend::Web(web::Ysdk::init(timeout).await?)));
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let _ = timeout;
+            Err(Error::Unsupported)
+        }
+    }
+
+    /// Sdk answering as scripted in the mock, never used unless asked for like in tests
+    pub fn mock(mock: Rc<Mock>) -> Self {
+        Self(Backend::Mock(mock))
+    }
+
     /// Informing the SDK that the game has loaded and is ready to play
     pub fn ready(&self) {
-        ysdk_sys::ready(&self.inner);
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web(sdk) => sdk.ready(),
+            Backend::Mock(mock) => mock.ready.set(true),
+        }
+    }
+
+    /// Player started playing, like after the start screen or unpausing
+    pub fn gameplay_start(&self) {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web(sdk) => sdk.gameplay_start(),
+            Backend::Mock(mock) => mock.gameplay.set(true),
+        }
+    }
+
+    /// Player stopped playing, like on death or pausing
+    pub fn gameplay_stop(&self) {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web(sdk) => sdk.gameplay_stop(),
+            Backend::Mock(mock) => mock.gameplay.set(false),
+        }
+    }
+
+    /// Events received since the last call, oldest first, None when there are no more
+    pub fn next_event(&self) -> Option<Event> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web(sdk) => sdk.next_event(),
+            Backend::Mock(mock) => mock.events.borrow_mut().pop_front(),
+        }
     }
 
     pub async fn player(&self, scopes: bool) -> Result<Player, Error> {
-        match wasm_bindgen_futures::JsFuture::from(ysdk_sys::get_player(&self.inner, scopes)).await
-        {
-            Ok(player) => Ok(Player(player)),
-            Err(err) => Err(Error::Js(err)),
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web(sdk) => Ok(Player(PlayerBackend::Web(sdk.player(scopes).await?))),
+            Backend::Mock(mock) => {
+                let _ = scopes;
+                Ok(Player(PlayerBackend::Mock(mock.clone())))
+            }
         }
     }
 
+    /// Returns whether the ad was shown
     pub async fn show_fullscreen_adv(&self) -> Result<bool, Error> {
-        let (sender, receiver) = async_oneshot::oneshot();
-        let sender = Rc::new(RefCell::new(sender));
-        let on_close: js_sys::Function = wasm_bindgen::closure::Closure::once_into_js({
-            let sender = sender.clone();
-            move |was_shown: bool| {
-                let _ = sender.borrow_mut().send(Ok(was_shown));
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web(sdk) => sdk.show_fullscreen_adv().await,
+            Backend::Mock(mock) => mock.show_fullscreen_adv(),
+        }
+    }
+
+    /// Returns whether the reward was earned by watching till the end
+    pub async fn show_rewarded_video(&self) -> Result<bool, Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web(sdk) => sdk.show_rewarded_video().await,
+            Backend::Mock(mock) => mock.show_rewarded_video(),
+        }
+    }
+
+    pub async fn leaderboards(&self) -> Result<Leaderboards, Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web(sdk) => Ok(Leaderboards(LeaderboardsBackend::Web(
+                sdk.leaderboards().await?,
+            ))),
+            Backend::Mock(mock) => Ok(Leaderboards(LeaderboardsBackend::Mock(mock.clone()))),
+        }
+    }
+
+    /// Fails with [Error::PaymentsUnavailable] if purchases are not enabled for the game
+    pub async fn payments(&self) -> Result<Payments, Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web(sdk) => Ok(Payments(PaymentsBackend::Web(sdk.payments().await?))),
+            Backend::Mock(mock) => {
+                mock.payments()?;
+                Ok(Payments(PaymentsBackend::Mock(mock.clone())))
             }
+        }
+    }
+
+    /// Interface language of Yandex Games as an ISO 639-1 code, like `ru`
+    pub fn language(&self) -> String {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web(sdk) => sdk.language(),
+            Backend::Mock(mock) => mock
+                .language
+                .borrow()
+                .clone()
+                .unwrap_or_else(|| "en".to_owned()),
+        }
+    }
+
+    pub fn device_info(&self) -> DeviceInfo {
+        DeviceInfo(match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web(sdk) => sdk.device_type(),
+            Backend::Mock(mock) => mock.device_type.get(),
         })
-        .dyn_into()
-        .unwrap();
-        let on_error: js_sys::Function = wasm_bindgen::closure::Closure::once_into_js({
-            let sender = sender.clone();
-            move |error: JsValue| {
-                let _ = sender.borrow_mut().send(Err(Error::Js(error)));
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardPlayer {
+    pub unique_id: String,
+    pub public_name: String,
+    /// Not set for some players
+    pub lang: Option<String>,
+    pub avatar_small: Option<String>,
+    pub avatar_medium: Option<String>,
+    pub avatar_large: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardEntry {
+    pub score: i64,
+    pub rank: u32,
+    pub formatted_score: String,
+    pub extra_data: Option<String>,
+    pub player: LeaderboardPlayer,
+}
+
+/// Part of the leaderboard, ranks start from 0
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct LeaderboardRange {
+    pub start: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardEntries {
+    pub ranges: Vec<LeaderboardRange>,
+    /// Zero if the player is not in the leaderboard
+    pub user_rank: u32,
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LeaderboardEntriesOptions {
+    /// Also return entries around the player
+    pub include_user: bool,
+    /// Entries above and below the player, 1 to 10
+    pub quantity_around: u32,
+    /// Entries from the top, 1 to 20
+    pub quantity_top: u32,
+}
+
+impl Default for LeaderboardEntriesOptions {
+    fn default() -> Self {
+        Self {
+            include_user: false,
+            quantity_around: 5,
+            quantity_top: 5,
+        }
+    }
+}
+
+enum LeaderboardsBackend {
+    #[cfg(target_arch = "wasm32")]
+    Web(web::Leaderboards),
+    Mock(Rc<Mock>),
+}
+
+pub struct Leaderboards(LeaderboardsBackend);
+
+impl Leaderboards {
+    /// Requires an authorized player, at most once per second
+    pub async fn set_score(
+        &self,
+        name: &str,
+        score: i64,
+        extra_data: Option<&str>,
+    ) -> Result<(), Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            LeaderboardsBackend::Web(leaderboards) => {
+                leaderboards.set_score(name, score, extra_data).await
             }
-        })
-        .dyn_into()
-        .unwrap();
-        let on_offline: js_sys::Function = wasm_bindgen::closure::Closure::once_into_js({
-            let sender = sender.clone();
-            move || {
-                let _ = sender.borrow_mut().send(Err(Error::Offline));
+            LeaderboardsBackend::Mock(mock) => {
+                let _ = extra_data;
+                mock.set_score(name, score)
             }
-        })
-        .dyn_into()
-        .unwrap();
-        ysdk_sys::show_fullscreen_adv(
-            &self.inner,
-            Some(on_close),
-            None,
-            Some(on_error),
-            Some(on_offline),
-        );
-        receiver.await.unwrap()
+        }
+    }
+
+    /// None if the player has no score in this leaderboard
+    pub async fn player_entry(&self, name: &str) -> Result<Option<LeaderboardEntry>, Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            LeaderboardsBackend::Web(leaderboards) => leaderboards.player_entry(name).await,
+            LeaderboardsBackend::Mock(mock) => mock.player_entry(name),
+        }
+    }
+
+    pub async fn entries(
+        &self,
+        name: &str,
+        options: &LeaderboardEntriesOptions,
+    ) -> Result<LeaderboardEntries, Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            LeaderboardsBackend::Web(leaderboards) => leaderboards.entries(name, options).await,
+            LeaderboardsBackend::Mock(mock) => mock.entries(name, options),
+        }
     }
 }
 
-pub struct DeviceInfo(ysdk_sys::DeviceInfo);
+/// Sent by the SDK on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The game should stop the gameplay and sounds, like when the tab is hidden or an ad is shown
+    Pause,
+    /// The pause is over
+    Resume,
+}
 
-impl Ysdk {
-    pub fn device_info(&self) -> DeviceInfo {
-        DeviceInfo(self.inner.deviceInfo())
+/// Item of the in-app purchases catalog
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Product {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub image_uri: String,
+    /// Formatted with the currency, like `10 YAN`
+    pub price: String,
+    pub price_value: String,
+    pub price_currency_code: String,
+}
+
+/// Paid product, stays in [Payments::purchases] until consumed
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Purchase {
+    pub product_id: String,
+    pub purchase_token: String,
+    pub developer_payload: Option<String>,
+}
+
+enum PaymentsBackend {
+    #[cfg(target_arch = "wasm32")]
+    Web(web::Payments),
+    Mock(Rc<Mock>),
+}
+
+pub struct Payments(PaymentsBackend);
+
+impl Payments {
+    pub async fn catalog(&self) -> Result<Vec<Product>, Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            PaymentsBackend::Web(payments) => payments.catalog().await,
+            PaymentsBackend::Mock(mock) => mock.catalog(),
+        }
+    }
+
+    /// Fails with [Error::PurchaseCancelled] if the player closes the payment window
+    pub async fn purchase(
+        &self,
+        id: &str,
+        developer_payload: Option<&str>,
+    ) -> Result<Purchase, Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            PaymentsBackend::Web(payments) => payments.purchase(id, developer_payload).await,
+            PaymentsBackend::Mock(mock) => mock.purchase(id, developer_payload),
+        }
+    }
+
+    /// Purchases that were not consumed yet, like when the game closed right after paying
+    pub async fn purchases(&self) -> Result<Vec<Purchase>, Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            PaymentsBackend::Web(payments) => payments.purchases().await,
+            PaymentsBackend::Mock(mock) => mock.purchases(),
+        }
+    }
+
+    /// Marks the purchase as credited so it can be bought again
+    pub async fn consume(&self, purchase: &Purchase) -> Result<(), Error> {
+        match &self.0 {
+            #[cfg(target_arch = "wasm32")]
+            PaymentsBackend::Web(payments) => payments.consume(&purchase.purchase_token).await,
+            PaymentsBackend::Mock(mock) => mock.consume(&purchase.purchase_token),
+        }
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeviceType {
+    #[default]
+    Desktop,
+    Mobile,
+    Tablet,
+}
+
+pub struct DeviceInfo(DeviceType);
+
 impl DeviceInfo {
+    pub fn device_type(&self) -> DeviceType {
+        self.0
+    }
     pub fn is_mobile(&self) -> bool {
-        self.0.isMobile()
+        self.0 == DeviceType::Mobile
     }
     pub fn is_desktop(&self) -> bool {
-        self.0.isDesktop()
+        self.0 == DeviceType::Desktop
     }
     pub fn is_tablet(&self) -> bool {
-        self.0.isTablet()
+        self.0 == DeviceType::Tablet
     }
 }
 
@@ -156,46 +449,22 @@ pub enum Error {
     Js(JsValue),
     #[error("offline")]
     Offline,
+    #[error("unexpected response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unexpected value: {0}")]
+    UnexpectedValue(String),
+    #[error("failed to load the SDK script")]
+    ScriptLoad,
+    #[error("the SDK only runs in the browser")]
+    Unsupported,
+    #[error("timed out")]
+    Timeout,
+    #[error("payments are not available")]
+    PaymentsUnavailable,
+    #[error("purchase cancelled")]
+    PurchaseCancelled,
+    #[error("unknown product: {0}")]
+    UnknownProduct(String),
     #[error("unknown")]
     Unknown,
 }
-
-impl Ysdk {
-    pub async fn init() -> Result<Self, Error> {
-        let document = web_sys::window().unwrap().document().unwrap();
-        let existing_script = document
-            .get_elements_by_tag_name("script")
-            .get_with_index(0)
-            .unwrap();
-        let new_script: web_sys::HtmlScriptElement = document
-            .create_element("script")
-            .unwrap()
-            .dyn_into()
-            .unwrap();
-        new_script.set_src("https://yandex.ru/games/sdk/v2");
-        new_script.set_async(true);
-        existing_script
-            .parent_node()
-            .unwrap()
-            .insert_before(&new_script, Some(&existing_script))
-            .unwrap();
-
-        let (mut sender, receiver) = async_oneshot::oneshot();
-        let on_load =
-            wasm_bindgen::closure::Closure::once_into_js(move |_event: web_sys::Event| {
-                let _ = sender.send(());
-            });
-        new_script.set_onload(Some(&on_load.dyn_into().unwrap()));
-        let () = receiver
-            .await
-            .map_err(|async_oneshot::Closed()| Error::Unknown)?;
-        Ok(Self {
-            inner: {
-                let obj = wasm_bindgen_futures::JsFuture::from(ysdk_sys::YaGames::init())
-                    .await
-                    .unwrap();
-                obj.unchecked_into()
-            },
-        })
-    }
-}
@@ -0,0 +1,402 @@
+use super::*;
+
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+};
+
+/// Unique id and public name of the mocked player
+pub const MOCK_PLAYER: &str = "mock";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdOutcome {
+    /// Shown and closed by the player
+    Shown,
+    /// Closed without showing, like when ads are shown too often
+    NotShown,
+    Error,
+    Offline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RewardedOutcome {
+    /// Watched till the end
+    Rewarded,
+    /// Closed before the reward
+    Closed,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PurchaseOutcome {
+    /// Paid, the purchase stays unconsumed until consumed
+    Purchased,
+    /// Payment window closed by the player
+    Cancelled,
+    Error,
+}
+
+/// Scripted SDK behavior, shared with everything the mocked [Ysdk] returns.
+///
+/// Can be changed at any time through the [Rc] passed to [Ysdk::mock]
+#[derive(Debug, Default)]
+pub struct Mock {
+    pub device_type: Cell<DeviceType>,
+    /// Language of the environment, `en` if not set
+    pub language: RefCell<Option<String>>,
+    /// Every request fails with [Error::Offline]
+    pub offline: Cell<bool>,
+    /// Outcomes of the next fullscreen ads, shown when the queue is empty
+    pub fullscreen_ads: RefCell<VecDeque<AdOutcome>>,
+    /// Outcomes of the next rewarded videos, rewarded when the queue is empty
+    pub rewarded_videos: RefCell<VecDeque<RewardedOutcome>>,
+    pub stats: RefCell<HashMap<String, f64>>,
+    /// Player data as JSON
+    pub data: RefCell<Option<String>>,
+    /// Scores of other players by leaderboard name
+    pub leaderboards: RefCell<HashMap<String, Vec<(String, i64)>>>,
+    /// Payments are not enabled for the game
+    pub payments_unavailable: Cell<bool>,
+    pub catalog: RefCell<Vec<Product>>,
+    /// Outcomes of the next purchases, purchased when the queue is empty
+    pub purchase_outcomes: RefCell<VecDeque<PurchaseOutcome>>,
+    /// Purchases that were not consumed yet
+    pub purchases: RefCell<Vec<Purchase>>,
+    /// Number of purchases made, used for the tokens
+    pub purchases_made: Cell<usize>,
+    /// Whether the game reported being ready
+    pub ready: Cell<bool>,
+    /// Between gameplay start and stop
+    pub gameplay: Cell<bool>,
+    /// Events the game gets next
+    pub events: RefCell<VecDeque<Event>>,
+    /// Number of ads shown, fullscreen and rewarded
+    pub ads_shown: Cell<usize>,
+}
+
+impl Mock {
+    fn check_online(&self) -> Result<(), Error> {
+        if self.offline.get() {
+            return Err(Error::Offline);
+        }
+        Ok(())
+    }
+
+    fn mock_error() -> Error {
+        Error::UnexpectedValue("mocked error".to_owned())
+    }
+
+    pub(crate) fn show_fullscreen_adv(&self) -> Result<bool, Error> {
+        self.check_online()?;
+        let outcome = self.fullscreen_ads.borrow_mut().pop_front();
+        match outcome.unwrap_or(AdOutcome::Shown) {
+            AdOutcome::Shown => {
+                self.ads_shown.set(self.ads_shown.get() + 1);
+                Ok(true)
+            }
+            AdOutcome::NotShown => Ok(false),
+            AdOutcome::Error => Err(Self::mock_error()),
+            AdOutcome::Offline => Err(Error::Offline),
+        }
+    }
+
+    pub(crate) fn show_rewarded_video(&self) -> Result<bool, Error> {
+        self.check_online()?;
+        let outcome = self.rewarded_videos.borrow_mut().pop_front();
+        match outcome.unwrap_or(RewardedOutcome::Rewarded) {
+            RewardedOutcome::Rewarded => {
+                self.ads_shown.set(self.ads_shown.get() + 1);
+                Ok(true)
+            }
+            RewardedOutcome::Closed => {
+                self.ads_shown.set(self.ads_shown.get() + 1);
+                Ok(false)
+            }
+            RewardedOutcome::Error => Err(Self::mock_error()),
+        }
+    }
+
+    pub(crate) fn numeric_data(&self, key: &str) -> Result<Option<f64>, Error> {
+        self.check_online()?;
+        Ok(self.stats.borrow().get(key).copied())
+    }
+
+    pub(crate) fn set_numeric_data(&self, key: &str, value: f64) -> Result<(), Error> {
+        self.check_online()?;
+        self.stats.borrow_mut().insert(key.to_owned(), value);
+        Ok(())
+    }
+
+    pub(crate) fn set_stats(&self, stats: &[(&str, f64)]) -> Result<(), Error> {
+        self.check_online()?;
+        let mut all = self.stats.borrow_mut();
+        for &(key, value) in stats {
+            all.insert(key.to_owned(), value);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn data(&self) -> Result<Option<String>, Error> {
+        self.check_online()?;
+        Ok(self.data.borrow().clone())
+    }
+
+    pub(crate) fn set_data(&self, json: &str) -> Result<(), Error> {
+        self.check_online()?;
+        *self.data.borrow_mut() = Some(json.to_owned());
+        Ok(())
+    }
+
+    pub(crate) fn set_score(&self, name: &str, score: i64) -> Result<(), Error> {
+        self.check_online()?;
+        let mut leaderboards = self.leaderboards.borrow_mut();
+        let scores = leaderboards.entry(name.to_owned()).or_default();
+        scores.retain(|(player, _)| player != MOCK_PLAYER);
+        scores.push((MOCK_PLAYER.to_owned(), score));
+        Ok(())
+    }
+
+    /// All entries sorted by rank
+    fn ranked(&self, name: &str) -> Vec<LeaderboardEntry> {
+        let mut scores = self
+            .leaderboards
+            .borrow()
+            .get(name)
+            .cloned()
+            .unwrap_or_default();
+        scores.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        scores
+            .into_iter()
+            .enumerate()
+            .map(|(index, (player, score))| LeaderboardEntry {
+                score,
+                rank: index as u32 + 1,
+                formatted_score: score.to_string(),
+                extra_data: None,
+                player: LeaderboardPlayer {
+                    unique_id: player.clone(),
+                    public_name: player,
+                    lang: Some("en".to_owned()),
+                    avatar_small: None,
+                    avatar_medium: None,
+                    avatar_large: None,
+                },
+            })
+            .collect()
+    }
+
+    pub(crate) fn player_entry(&self, name: &str) -> Result<Option<LeaderboardEntry>, Error> {
+        self.check_online()?;
+        Ok(self
+            .ranked(name)
+            .into_iter()
+            .find(|entry| entry.player.unique_id == MOCK_PLAYER))
+    }
+
+    pub(crate) fn entries(
+        &self,
+        name: &str,
+        options: &LeaderboardEntriesOptions,
+    ) -> Result<LeaderboardEntries, Error> {
+        self.check_online()?;
+        let ranked = self.ranked(name);
+        let mut ranges = vec![LeaderboardRange {
+            start: 0,
+            size: (options.quantity_top as usize).min(ranked.len()) as u32,
+        }];
+        let user_index = ranked
+            .iter()
+            .position(|entry| entry.player.unique_id == MOCK_PLAYER);
+        if let (true, Some(index)) = (options.include_user, user_index) {
+            let start = index.saturating_sub(options.quantity_around as usize);
+            let end = (index + options.quantity_around as usize + 1).min(ranked.len());
+            ranges.push(LeaderboardRange {
+                start: start as u32,
+                size: (end - start) as u32,
+            });
+        }
+        let mut indices: Vec<usize> = ranges
+            .iter()
+            .flat_map(|range| range.start as usize..(range.start + range.size) as usize)
+            .collect();
+        indices.sort();
+        indices.dedup();
+        Ok(LeaderboardEntries {
+            ranges,
+            user_rank: user_index.map_or(0, |index| index as u32 + 1),
+            entries: indices
+                .into_iter()
+                .map(|index| ranked[index].clone())
+                .collect(),
+        })
+    }
+
+    pub(crate) fn payments(&self) -> Result<(), Error> {
+        self.check_online()?;
+        if self.payments_unavailable.get() {
+            return Err(Error::PaymentsUnavailable);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn catalog(&self) -> Result<Vec<Product>, Error> {
+        self.check_online()?;
+        Ok(self.catalog.borrow().clone())
+    }
+
+    pub(crate) fn purchase(
+        &self,
+        id: &str,
+        developer_payload: Option<&str>,
+    ) -> Result<Purchase, Error> {
+        self.check_online()?;
+        if !self.catalog.borrow().iter().any(|product| product.id == id) {
+            return Err(Error::UnknownProduct(id.to_owned()));
+        }
+        let outcome = self.purchase_outcomes.borrow_mut().pop_front();
+        match outcome.unwrap_or(PurchaseOutcome::Purchased) {
+            PurchaseOutcome::Purchased => {
+                self.purchases_made.set(self.purchases_made.get() + 1);
+                let purchase = Purchase {
+                    product_id: id.to_owned(),
+                    purchase_token: format!("mock-{}", self.purchases_made.get()),
+                    developer_payload: developer_payload.map(str::to_owned),
+                };
+                self.purchases.borrow_mut().push(purchase.clone());
+                Ok(purchase)
+            }
+            PurchaseOutcome::Cancelled => Err(Error::PurchaseCancelled),
+            PurchaseOutcome::Error => Err(Self::mock_error()),
+        }
+    }
+
+    pub(crate) fn purchases(&self) -> Result<Vec<Purchase>, Error> {
+        self.check_online()?;
+        Ok(self.purchases.borrow().clone())
+    }
+
+    pub(crate) fn consume(&self, token: &str) -> Result<(), Error> {
+        self.check_online()?;
+        let mut purchases = self.purchases.borrow_mut();
+        let count = purchases.len();
+        purchases.retain(|purchase| purchase.purchase_token != token);
+        if purchases.len() == count {
+            return Err(Error::UnexpectedValue(format!(
+                "no purchase with token {token}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+
+    fn product(id: &str) -> Product {
+        Product {
+            id: id.to_owned(),
+            title: id.to_owned(),
+            description: String::new(),
+            image_uri: String::new(),
+            price: "10 YAN".to_owned(),
+            price_value: "10".to_owned(),
+            price_currency_code: "YAN".to_owned(),
+        }
+    }
+
+    #[test]
+    fn no_mock_without_asking() {
+        assert!(matches!(
+            block_on(Ysdk::init(Duration::from_secs(1))),
+            Err(Error::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn rewarded_video() {
+        let mock = Rc::new(Mock::default());
+        mock.rewarded_videos
+            .borrow_mut()
+            .extend([RewardedOutcome::Rewarded, RewardedOutcome::Closed]);
+        let sdk = Ysdk::mock(mock.clone());
+        assert!(block_on(sdk.show_rewarded_video()).unwrap());
+        assert!(!block_on(sdk.show_rewarded_video()).unwrap());
+        mock.rewarded_videos
+            .borrow_mut()
+            .push_back(RewardedOutcome::Error);
+        assert!(block_on(sdk.show_rewarded_video()).is_err());
+        assert_eq!(mock.ads_shown.get(), 2);
+        // Rewarded once the script runs out
+        assert!(block_on(sdk.show_rewarded_video()).unwrap());
+
+        mock.offline.set(true);
+        assert!(matches!(
+            block_on(sdk.show_rewarded_video()),
+            Err(Error::Offline)
+        ));
+        assert_eq!(mock.ads_shown.get(), 3);
+    }
+
+    #[test]
+    fn player_data_round_trip() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Data {
+            money: u64,
+            name: String,
+        }
+
+        let mock = Rc::new(Mock::default());
+        let sdk = Ysdk::mock(mock.clone());
+        let player = block_on(sdk.player(false)).unwrap();
+        assert_eq!(block_on(player.data::<Data>()).unwrap(), None);
+        let data = Data {
+            money: 42,
+            name: "cat".to_owned(),
+        };
+        block_on(player.set_data(&data, true)).unwrap();
+        assert_eq!(block_on(player.data::<Data>()).unwrap(), Some(data));
+
+        mock.offline.set(true);
+        assert!(matches!(
+            block_on(player.data::<Data>()),
+            Err(Error::Offline)
+        ));
+    }
+
+    #[test]
+    fn cancelled_purchase() {
+        let mock = Rc::new(Mock::default());
+        mock.catalog.borrow_mut().push(product("coins"));
+        mock.purchase_outcomes
+            .borrow_mut()
+            .push_back(PurchaseOutcome::Cancelled);
+        let sdk = Ysdk::mock(mock.clone());
+        let payments = block_on(sdk.payments()).unwrap();
+        assert!(matches!(
+            block_on(payments.purchase("coins", None)),
+            Err(Error::PurchaseCancelled)
+        ));
+        assert!(block_on(payments.purchases()).unwrap().is_empty());
+
+        let purchase = block_on(payments.purchase("coins", None)).unwrap();
+        assert_eq!(block_on(payments.purchases()).unwrap().len(), 1);
+        block_on(payments.consume(&purchase)).unwrap();
+        assert!(block_on(payments.purchases()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn events_in_order() {
+        let mock = Rc::new(Mock::default());
+        let sdk = Ysdk::mock(mock.clone());
+        assert!(sdk.next_event().is_none());
+        mock.events
+            .borrow_mut()
+            .extend([Event::Pause, Event::Resume]);
+        assert!(matches!(sdk.next_event(), Some(Event::Pause)));
+        assert!(matches!(sdk.next_event(), Some(Event::Resume)));
+        assert!(sdk.next_event().is_none());
+    }
+}
@@ -0,0 +1,509 @@
+use super::*;
+
+use std::{collections::VecDeque, future::Future, pin::Pin, task::Poll, time::Duration};
+
+mod ysdk_sys {
+    use super::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        pub type YaGames;
+
+        #[wasm_bindgen(js_namespace = YaGames)]
+        pub fn init() -> js_sys::Promise;
+
+        pub type DeviceInfo;
+
+        #[wasm_bindgen(method, getter)]
+        pub fn deviceInfo(this: &YaGames) -> DeviceInfo;
+
+        #[wasm_bindgen(method)]
+        pub fn isMobile(this: &DeviceInfo) -> bool;
+        #[wasm_bindgen(method)]
+        pub fn isDesktop(this: &DeviceInfo) -> bool;
+        #[wasm_bindgen(method)]
+        pub fn isTablet(this: &DeviceInfo) -> bool;
+    }
+
+    #[wasm_bindgen(module = "/src/lib.js")]
+    extern "C" {
+        pub fn ready(ysdk: &YaGames);
+        pub fn gameplay_start(ysdk: &YaGames);
+        pub fn gameplay_stop(ysdk: &YaGames);
+        pub fn environment_lang(ysdk: &YaGames) -> String;
+        pub fn on_pause_resume(ysdk: &YaGames, on_pause: &JsValue, on_resume: &JsValue);
+        pub fn show_fullscreen_adv(
+            ysdk: &YaGames,
+            on_close: Option<js_sys::Function>,
+            on_open: Option<js_sys::Function>,
+            on_error: Option<js_sys::Function>,
+            on_offline: Option<js_sys::Function>,
+        );
+        pub fn show_rewarded_video(
+            ysdk: &YaGames,
+            on_open: Option<js_sys::Function>,
+            on_rewarded: Option<js_sys::Function>,
+            on_close: Option<js_sys::Function>,
+            on_error: Option<js_sys::Function>,
+        );
+        pub fn get_player(ysdk: &YaGames, scopes: bool) -> js_sys::Promise;
+        pub fn player_unique_id(player: &JsValue) -> String;
+        #[wasm_bindgen(catch)]
+        pub async fn get_player_numeric_data(
+            player: &JsValue,
+            key: &str,
+        ) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn set_player_numeric_data(
+            player: &JsValue,
+            key: &str,
+            value: f64,
+        ) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn set_player_stats(player: &JsValue, json: &str) -> Result<JsValue, JsValue>;
+        pub fn get_leaderboards(ysdk: &YaGames) -> js_sys::Promise;
+        #[wasm_bindgen(catch)]
+        pub async fn set_leaderboard_score(
+            leaderboards: &JsValue,
+            name: &str,
+            score: f64,
+            extra_data: Option<String>,
+        ) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn get_leaderboard_player_entry(
+            leaderboards: &JsValue,
+            name: &str,
+        ) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn get_leaderboard_entries(
+            leaderboards: &JsValue,
+            name: &str,
+            include_user: bool,
+            quantity_around: u32,
+            quantity_top: u32,
+        ) -> Result<JsValue, JsValue>;
+        pub fn get_payments(ysdk: &YaGames) -> js_sys::Promise;
+        #[wasm_bindgen(catch)]
+        pub async fn get_catalog(payments: &JsValue) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn purchase(
+            payments: &JsValue,
+            id: &str,
+            developer_payload: Option<String>,
+        ) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn get_purchases(payments: &JsValue) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn consume_purchase(payments: &JsValue, token: &str) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn get_player_data(player: &JsValue) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        pub async fn set_player_data(
+            player: &JsValue,
+            json: &str,
+            flush: bool,
+        ) -> Result<JsValue, JsValue>;
+    }
+}
+
+pub struct Player(JsValue);
+
+impl Player {
+    pub fn unique_id(&self) -> String {
+        ysdk_sys::player_unique_id(&self.0)
+    }
+    pub async fn numeric_data(&self, key: &str) -> Result<Option<f64>, Error> {
+        let result = ysdk_sys::get_player_numeric_data(&self.0, key)
+            .await
+            .map_err(Error::Js)?;
+        if let Some(value) = result.as_f64() {
+            return Ok(Some(value));
+        }
+        if result.is_null() || result.is_undefined() {
+            return Ok(None);
+        }
+        Err(Error::UnexpectedValue(format!("{result:?}")))
+    }
+    pub async fn set_numeric_data(&self, key: &str, value: f64) -> Result<(), Error> {
+        match ysdk_sys::set_player_numeric_data(&self.0, key, value).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::Js(e)),
+        }
+    }
+    pub async fn set_stats(&self, json: &str) -> Result<(), Error> {
+        match ysdk_sys::set_player_stats(&self.0, json).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::Js(e)),
+        }
+    }
+    pub async fn data(&self) -> Result<Option<String>, Error> {
+        let result = ysdk_sys::get_player_data(&self.0)
+            .await
+            .map_err(Error::Js)?;
+        if result.is_null() {
+            return Ok(None);
+        }
+        match result.as_string() {
+            Some(json) => Ok(Some(json)),
+            None => Err(Error::UnexpectedValue(format!("{result:?}"))),
+        }
+    }
+    pub async fn set_data(&self, json: &str, flush: bool) -> Result<(), Error> {
+        match ysdk_sys::set_player_data(&self.0, json, flush).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::Js(e)),
+        }
+    }
+}
+
+pub struct Leaderboards(JsValue);
+
+impl Leaderboards {
+    pub async fn set_score(
+        &self,
+        name: &str,
+        score: i64,
+        extra_data: Option<&str>,
+    ) -> Result<(), Error> {
+        match ysdk_sys::set_leaderboard_score(
+            &self.0,
+            name,
+            score as f64,
+            extra_data.map(str::to_owned),
+        )
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::Js(e)),
+        }
+    }
+
+    pub async fn player_entry(&self, name: &str) -> Result<Option<LeaderboardEntry>, Error> {
+        match ysdk_sys::get_leaderboard_player_entry(&self.0, name).await {
+            Ok(json) => match json.as_string() {
+                Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+                None => Ok(None),
+            },
+            Err(e) => Err(Error::Js(e)),
+        }
+    }
+
+    pub async fn entries(
+        &self,
+        name: &str,
+        options: &LeaderboardEntriesOptions,
+    ) -> Result<LeaderboardEntries, Error> {
+        match ysdk_sys::get_leaderboard_entries(
+            &self.0,
+            name,
+            options.include_user,
+            options.quantity_around,
+            options.quantity_top,
+        )
+        .await
+        {
+            Ok(json) => {
+                let json = json.as_string().ok_or(Error::Unknown)?;
+                Ok(serde_json::from_str(&json)?)
+            }
+            Err(e) => Err(Error::Js(e)),
+        }
+    }
+}
+
+pub struct Payments(JsValue);
+
+impl Payments {
+    pub async fn catalog(&self) -> Result<Vec<Product>, Error> {
+        let json = ysdk_sys::get_catalog(&self.0).await.map_err(Error::Js)?;
+        let json = json.as_string().ok_or(Error::Unknown)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub async fn purchase(
+        &self,
+        id: &str,
+        developer_payload: Option<&str>,
+    ) -> Result<Purchase, Error> {
+        let json = ysdk_sys::purchase(&self.0, id, developer_payload.map(str::to_owned))
+            .await
+            .map_err(Error::Js)?;
+        match json.as_string() {
+            Some(json) => Ok(serde_json::from_str(&json)?),
+            None => Err(Error::PurchaseCancelled),
+        }
+    }
+
+    pub async fn purchases(&self) -> Result<Vec<Purchase>, Error> {
+        let json = ysdk_sys::get_purchases(&self.0).await.map_err(Error::Js)?;
+        let json = json.as_string().ok_or(Error::Unknown)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub async fn consume(&self, token: &str) -> Result<(), Error> {
+        match ysdk_sys::consume_purchase(&self.0, token).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::Js(e)),
+        }
+    }
+}
+
+type InitSender = Rc<RefCell<async_oneshot::Sender<Result<JsValue, Error>>>>;
+
+const SDK_URL: &str = "https://yandex.ru/games/sdk/v2";
+
+/// Sends the initialized `YaGames` once its promise resolves
+fn init_sdk(sender: InitSender) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let result = wasm_bindgen_futures::JsFuture::from(ysdk_sys::YaGames::init())
+            .await
+            .map_err(Error::Js);
+        let _ = sender.borrow_mut().send(result);
+    });
+}
+
+/// Inserts the SDK script before the first script like the snippet from the docs,
+/// into the document element if the page has none
+fn load_script(window: &web_sys::Window, sender: InitSender) -> Result<(), Error> {
+    let document = window.document().ok_or(Error::Unknown)?;
+    let script: web_sys::HtmlScriptElement = document
+        .create_element("script")
+        .map_err(Error::Js)?
+        .dyn_into()
+        .map_err(|_| Error::Unknown)?;
+    script.set_src(SDK_URL);
+    script.set_async(true);
+    let on_load = wasm_bindgen::closure::Closure::once_into_js({
+        let sender = sender.clone();
+        move |_event: web_sys::Event| init_sdk(sender)
+    });
+    script.set_onload(Some(on_load.unchecked_ref()));
+    let on_error = wasm_bindgen::closure::Closure::once_into_js(move |_event: web_sys::Event| {
+        let _ = sender.borrow_mut().send(Err(Error::ScriptLoad));
+    });
+    script.set_onerror(Some(on_error.unchecked_ref()));
+    match document
+        .get_elements_by_tag_name("script")
+        .get_with_index(0)
+    {
+        Some(first) => first
+            .parent_node()
+            .ok_or(Error::Unknown)?
+            .insert_before(&script, Some(&first)),
+        None => document
+            .document_element()
+            .ok_or(Error::Unknown)?
+            .append_child(&script),
+    }
+    .map_err(Error::Js)?;
+    Ok(())
+}
+
+pub async fn timeout<T>(
+    timeout: Duration,
+    future: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    let (mut sender, mut receiver) = async_oneshot::oneshot::<()>();
+    let on_timeout = wasm_bindgen::closure::Closure::once_into_js(move || {
+        let _ = sender.send(());
+    });
+    web_sys::window()
+        .ok_or(Error::Unknown)?
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            on_timeout.unchecked_ref(),
+            timeout.as_millis().try_into().unwrap_or(i32::MAX),
+        )
+        .map_err(Error::Js)?;
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        if let Poll::Ready(result) = future.as_mut().poll(cx) {
+            return Poll::Ready(result);
+        }
+        Pin::new(&mut receiver)
+            .poll(cx)
+            .map(|_| Err(Error::Timeout))
+    })
+    .await
+}
+
+pub struct Ysdk {
+    inner: ysdk_sys::YaGames,
+    events: Rc<RefCell<VecDeque<Event>>>,
+}
+
+impl Ysdk {
+    pub async fn init(timeout: Duration) -> Result<Self, Error> {
+        let (sender, receiver) = async_oneshot::oneshot();
+        let sender: InitSender = Rc::new(RefCell::new(sender));
+        let window = web_sys::window().ok_or(Error::Unknown)?;
+        let on_timeout = wasm_bindgen::closure::Closure::once_into_js({
+            let sender = sender.clone();
+            move || {
+                let _ = sender.borrow_mut().send(Err(Error::Timeout));
+            }
+        });
+        window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                on_timeout.unchecked_ref(),
+                timeout.as_millis().try_into().unwrap_or(i32::MAX),
+            )
+            .map_err(Error::Js)?;
+        let loaded =
+            js_sys::Reflect::has(&js_sys::global(), &JsValue::from_str("YaGames")).unwrap_or(false);
+        if loaded {
+            // Included by the page itself
+            init_sdk(sender);
+        } else {
+            load_script(&window, sender)?;
+        }
+        let inner: ysdk_sys::YaGames = receiver
+            .await
+            .map_err(|async_oneshot::Closed()| Error::Unknown)??
+            .unchecked_into();
+        let events = Rc::new(RefCell::new(VecDeque::new()));
+        let listener = |event: Event| {
+            let events = events.clone();
+            wasm_bindgen::closure::Closure::<dyn FnMut()>::new(move || {
+                events.borrow_mut().push_back(event);
+            })
+            .into_js_value()
+        };
+        ysdk_sys::on_pause_resume(&inner, &listener(Event::Pause), &listener(Event::Resume));
+        Ok(Self { inner, events })
+    }
+
+    pub fn gameplay_start(&self) {
+        ysdk_sys::gameplay_start(&self.inner);
+    }
+
+    pub fn gameplay_stop(&self) {
+        ysdk_sys::gameplay_stop(&self.inner);
+    }
+
+    pub fn next_event(&self) -> Option<Event> {
+        self.events.borrow_mut().pop_front()
+    }
+
+    pub fn ready(&self) {
+        ysdk_sys::ready(&self.inner);
+    }
+
+    pub async fn player(&self, scopes: bool) -> Result<Player, Error> {
+        match wasm_bindgen_futures::JsFuture::from(ysdk_sys::get_player(&self.inner, scopes)).await
+        {
+            Ok(player) => Ok(Player(player)),
+            Err(err) => Err(Error::Js(err)),
+        }
+    }
+
+    pub async fn leaderboards(&self) -> Result<Leaderboards, Error> {
+        match wasm_bindgen_futures::JsFuture::from(ysdk_sys::get_leaderboards(&self.inner)).await {
+            Ok(leaderboards) => Ok(Leaderboards(leaderboards)),
+            Err(err) => Err(Error::Js(err)),
+        }
+    }
+
+    pub async fn payments(&self) -> Result<Payments, Error> {
+        match wasm_bindgen_futures::JsFuture::from(ysdk_sys::get_payments(&self.inner)).await {
+            Ok(payments) => Ok(Payments(payments)),
+            Err(err) => {
+                log::warn!("payments are unavailable: {err:?}");
+                Err(Error::PaymentsUnavailable)
+            }
+        }
+    }
+
+    pub fn language(&self) -> String {
+        ysdk_sys::environment_lang(&self.inner)
+    }
+
+    pub fn device_type(&self) -> DeviceType {
+        let info = self.inner.deviceInfo();
+        if info.isMobile() {
+            DeviceType::Mobile
+        } else if info.isTablet() {
+            DeviceType::Tablet
+        } else {
+            DeviceType::Desktop
+        }
+    }
+
+    pub async fn show_fullscreen_adv(&self) -> Result<bool, Error> {
+        let (sender, receiver) = async_oneshot::oneshot();
+        let sender = Rc::new(RefCell::new(sender));
+        let on_close: js_sys::Function = wasm_bindgen::closure::Closure::once_into_js({
+            let sender = sender.clone();
+            move |was_shown: bool| {
+                let _ = sender.borrow_mut().send(Ok(was_shown));
+            }
+        })
+        .dyn_into()
+        .unwrap();
+        let on_error: js_sys::Function = wasm_bindgen::closure::Closure::once_into_js({
+            let sender = sender.clone();
+            move |error: JsValue| {
+                let _ = sender.borrow_mut().send(Err(Error::Js(error)));
+            }
+        })
+        .dyn_into()
+        .unwrap();
+        let on_offline: js_sys::Function = wasm_bindgen::closure::Closure::once_into_js({
+            let sender = sender.clone();
+            move || {
+                let _ = sender.borrow_mut().send(Err(Error::Offline));
+            }
+        })
+        .dyn_into()
+        .unwrap();
+        ysdk_sys::show_fullscreen_adv(
+            &self.inner,
+            Some(on_close),
+            None,
+            Some(on_error),
+            Some(on_offline),
+        );
+        receiver.await.unwrap()
+    }
+
+    /// Returns whether the reward was earned by watching till the end
+    pub async fn show_rewarded_video(&self) -> Result<bool, Error> {
+        let (sender, receiver) = async_oneshot::oneshot();
+        let sender = Rc::new(RefCell::new(sender));
+        let rewarded = Rc::new(std::cell::Cell::new(false));
+        let on_open: js_sys::Function = wasm_bindgen::closure::Closure::once_into_js(|| {
+            log::debug!("rewarded video opened");
+        })
+        .dyn_into()
+        .unwrap();
+        let on_rewarded: js_sys::Function = wasm_bindgen::closure::Closure::once_into_js({
+            let rewarded = rewarded.clone();
+            move || rewarded.set(true)
+        })
+        .dyn_into()
+        .unwrap();
+        let on_close: js_sys::Function = wasm_bindgen::closure::Closure::once_into_js({
+            let sender = sender.clone();
+            move || {
+                let _ = sender.borrow_mut().send(Ok(rewarded.get()));
+            }
+        })
+        .dyn_into()
+        .unwrap();
+        let on_error: js_sys::Function = wasm_bindgen::closure::Closure::once_into_js({
+            let sender = sender.clone();
+            move |error: JsValue| {
+                let _ = sender.borrow_mut().send(Err(Error::Js(error)));
+            }
+        })
+        .dyn_into()
+        .unwrap();
+        ysdk_sys::show_rewarded_video(
+            &self.inner,
+            Some(on_open),
+            Some(on_rewarded),
+            Some(on_close),
+            Some(on_error),
+        );
+        receiver.await.unwrap()
+    }
+}